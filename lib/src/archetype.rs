@@ -0,0 +1,215 @@
+//! Folder-based templates (i.e., archetypes) that create multiple files when a note is added.
+//!
+//! An archetype is a folder in the templates folder of the profile with an `_index` template.
+//! The index template is rendered as the note while the rest of the folder is rendered into the subject.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globwalk;
+use toml;
+
+use crate::error::Error;
+use crate::helpers;
+use crate::profile::{Profile, JINJA_TEMPLATE_FILE_EXTENSION, TEMPLATE_FILE_EXTENSION};
+use crate::templates::TemplateRegistry;
+use crate::Result;
+
+/// The file stem of the template for the note in the archetype.
+pub const ARCHETYPE_INDEX_NAME: &str = "_index";
+
+/// A folder-based template from the profile.
+#[derive(Debug, Clone)]
+pub struct Archetype {
+    name: String,
+    path: PathBuf,
+    index: String,
+}
+
+impl Archetype {
+    /// Opens the archetype with the given name from the templates folder of the profile.
+    ///
+    /// It will return `None` if there is no folder with the name or the folder does not have an index template.
+    pub fn from<S: AsRef<str>>(
+        name: S,
+        profile: &Profile,
+    ) -> Option<Self> {
+        let name = name.as_ref().trim_end_matches('/');
+        let path = profile.templates_path().join(name);
+
+        let index = fs::read_dir(&path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| template_name(&entry.file_name().to_string_lossy()))
+            .find(|index| {
+                index == ARCHETYPE_INDEX_NAME
+                    || index.starts_with(&format!("{}.", ARCHETYPE_INDEX_NAME))
+            })?;
+
+        Some(Self {
+            name: name.to_string(),
+            path,
+            index,
+        })
+    }
+
+    /// Returns the name of the archetype.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the index template as registered in the profile (e.g., `lab/_index.tex`).
+    pub fn index_template_name(&self) -> String {
+        format!("{}/{}", self.name, self.index)
+    }
+
+    /// Returns the file extension of the note from the index template, if there's any.
+    /// For example, the index template at `lab/_index.tex.hbs` creates a LaTeX document.
+    pub fn extension(&self) -> Option<String> {
+        Path::new(&self.index)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+    }
+
+    /// Renders the rest of the archetype into the folder (usually the subject of the note).
+    ///
+    /// The path of each file is a Handlebars template rendered with the same object as the templates (e.g., `{{note.stem}}.bib.hbs`).
+    /// The template files (i.e., `.hbs`, `.j2`) are rendered with their template engine while the other files are copied as they are.
+    /// In strict mode, the existing files are left untouched.
+    ///
+    /// Returns the paths of the created files and folders.
+    pub fn export<P: AsRef<Path>>(
+        &self,
+        profile: &Profile,
+        object: &toml::Value,
+        dst: P,
+        strict: bool,
+    ) -> Result<Vec<PathBuf>> {
+        let dst = dst.as_ref();
+        let registry = profile.template_registry();
+        let mut created_paths = vec![];
+
+        let files = globwalk::GlobWalkerBuilder::new(&self.path, "**")
+            .min_depth(1)
+            .sort_by(|a, b| a.path().cmp(b.path()))
+            .build()
+            .map_err(Error::GlobParsingError)?;
+        for file in files.flatten() {
+            let relpath = match helpers::fs::relative_path_from(file.path(), &self.path) {
+                Some(relpath) => relpath.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let template = template_name(&relpath);
+
+            if file.file_type().is_file() && template.as_ref() == Some(&self.index) {
+                continue;
+            }
+
+            let rendered_relpath =
+                registry.render_raw_template(template.as_ref().unwrap_or(&relpath), object)?;
+            let path = dst.join(rendered_relpath);
+
+            if file.file_type().is_dir() {
+                if !path.is_dir() {
+                    fs::create_dir_all(&path).map_err(Error::IoError)?;
+                    created_paths.push(path);
+                }
+
+                continue;
+            }
+
+            if strict && path.exists() {
+                continue;
+            }
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(Error::IoError)?;
+            }
+
+            match &template {
+                Some(template) => {
+                    let content = registry.render(format!("{}/{}", self.name, template), object)?;
+                    fs::write(&path, content).map_err(Error::IoError)?;
+                }
+                None => {
+                    fs::copy(file.path(), &path).map_err(Error::IoError)?;
+                }
+            }
+
+            created_paths.push(path);
+        }
+
+        Ok(created_paths)
+    }
+}
+
+/// Returns the template name of the file (i.e., the file name without the template file extension).
+/// It will return `None` if the file is not a template.
+fn template_name(file_name: &str) -> Option<String> {
+    [TEMPLATE_FILE_EXTENSION, JINJA_TEMPLATE_FILE_EXTENSION]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(&format!(".{}", extension)))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+    use tempfile;
+
+    #[test]
+    fn archetype_export() -> Result<()> {
+        let profile_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let mut profile_builder = ProfileBuilder::new();
+        profile_builder.path(profile_dir.path());
+        profile_builder.build().export()?;
+
+        let lab = profile_dir.path().join("templates").join("lab");
+        fs::create_dir_all(lab.join("figures")).map_err(Error::IoError)?;
+        fs::write(lab.join("_index.tex.hbs"), "\\title{ {{~note.title~}} }")
+            .map_err(Error::IoError)?;
+        fs::write(
+            lab.join("{{note.stem}}.bib.j2"),
+            "% {{ note.title | upper_case }}",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(lab.join("latexmkrc"), "$pdf_mode = 1;").map_err(Error::IoError)?;
+
+        let profile = Profile::from(profile_dir.path())?;
+        assert!(Archetype::from("master", &profile).is_none());
+
+        let archetype = Archetype::from("lab", &profile).unwrap();
+        assert_eq!(archetype.index_template_name(), "lab/_index.tex");
+        assert_eq!(archetype.extension(), Some("tex".to_string()));
+
+        let object: toml::Value =
+            toml::from_str("[note]\ntitle = 'Pendulum'\nstem = 'pendulum'").unwrap();
+        let dst = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let created_paths = archetype.export(&profile, &object, dst.path(), true)?;
+        assert_eq!(created_paths.len(), 3);
+        assert!(dst.path().join("figures").is_dir());
+        assert!(!dst.path().join("_index.tex").exists());
+        assert_eq!(
+            fs::read_to_string(dst.path().join("pendulum.bib")).map_err(Error::IoError)?,
+            "% PENDULUM"
+        );
+        assert_eq!(
+            fs::read_to_string(dst.path().join("latexmkrc")).map_err(Error::IoError)?,
+            "$pdf_mode = 1;"
+        );
+
+        // The existing files are kept in strict mode.
+        fs::write(dst.path().join("latexmkrc"), "").map_err(Error::IoError)?;
+        assert!(archetype
+            .export(&profile, &object, dst.path(), true)?
+            .is_empty());
+        assert_eq!(
+            fs::read_to_string(dst.path().join("latexmkrc")).map_err(Error::IoError)?,
+            ""
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,938 @@
+//! The compilation subsystem of Lanoma.
+//!
+//! The compilation process is done through a compilation environment which is associated with a subject.
+//! The notes (and master notes) are compiled in parallel with the compiler of the environment.
+//! The progress of the compilation can be observed by passing an observer.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::iter::Sum;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use toml;
+
+use crate::config::{CompileStep, Compiler, ShelfConfig, SubjectConfig};
+use crate::error::Error;
+use crate::helpers;
+use crate::masternote::MasterNote;
+use crate::note::Note;
+use crate::profile::Profile;
+use crate::shelf::{Shelf, ShelfData, ShelfItem};
+use crate::subjects::Subject;
+use crate::{Object, Result};
+
+use crate::modify_toml_table;
+
+pub type CompilableObject = Box<dyn Compilable>;
+
+/// A trait for the objects that can be compiled.
+pub trait Compilable: Send + Sync {
+    /// Returns the data of the object to be set as the `note` table of the command object.
+    fn data(
+        &self,
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> toml::Value;
+
+    fn name(&self) -> String;
+
+    /// Returns the file name of the object without the file extension.
+    /// This is used for getting the resulting artifacts (e.g., `{stem}.pdf`).
+    fn stem(&self) -> String;
+}
+
+impl Display for dyn Compilable + '_ {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Debug for dyn Compilable + '_ {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{:?}", self.name())
+    }
+}
+
+impl Compilable for MasterNote {
+    /// The master note is represented similarly to a note so the same command template can be used for both.
+    fn data(
+        &self,
+        _subject: &Subject,
+        shelf: &Shelf,
+    ) -> toml::Value {
+        let mut master_note_as_toml = ShelfData::data(self, shelf);
+
+        modify_toml_table! {master_note_as_toml,
+            ("title", self.subject().name()),
+            ("file", self.file_name()),
+            ("stem", Compilable::stem(self)),
+            ("path_in_shelf", self.path_in_shelf(shelf))
+        };
+
+        master_note_as_toml
+    }
+
+    fn name(&self) -> String {
+        self.subject().name()
+    }
+
+    fn stem(&self) -> String {
+        Path::new(&self.file_name())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl Compilable for Note {
+    fn data(
+        &self,
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> toml::Value {
+        ShelfData::data(self, (subject, shelf))
+    }
+
+    fn name(&self) -> String {
+        self.title()
+    }
+
+    fn stem(&self) -> String {
+        Note::stem(self)
+    }
+}
+
+/// A trait for observing the progress of the compilation process.
+///
+/// The events are sent from multiple threads so the observer has to be thread-safe.
+/// All of the methods do nothing by default.
+pub trait CompileObserver: Sync {
+    /// Called when the compilation of the compilable starts.
+    fn job_started(
+        &self,
+        _compilable: &dyn Compilable,
+    ) {
+    }
+
+    /// Called when the compilable is successfully compiled.
+    fn job_finished(
+        &self,
+        _compilable: &dyn Compilable,
+    ) {
+    }
+
+    /// Called when the compilable failed to compile.
+    fn job_failed(
+        &self,
+        _compilable: &dyn Compilable,
+        _failure: &StepFailure,
+    ) {
+    }
+
+    /// Called for each line of the output (both stdout and stderr) of the compilation commands.
+    fn output_line(
+        &self,
+        _compilable: &dyn Compilable,
+        _line: &str,
+    ) {
+    }
+}
+
+/// An observer that ignores all of the events.
+pub struct NoopObserver;
+
+impl CompileObserver for NoopObserver {}
+
+/// The details of a failed step from the compilation process.
+#[derive(Debug)]
+pub struct StepFailure {
+    /// The index of the step starting from zero.
+    pub index: usize,
+
+    /// The rendered command of the step.
+    pub command: String,
+
+    /// The reason of the failure.
+    pub error: Error,
+}
+
+/// A compilable that failed to compile.
+pub struct CompileFailure {
+    pub compilable: CompilableObject,
+    pub step: StepFailure,
+}
+
+/// The result from the compilation process of the compenv.
+pub struct CompileResult {
+    pub path: PathBuf,
+    pub compiled: Vec<CompilableObject>,
+    pub failed: Vec<CompileFailure>,
+
+    /// The compilables that are not compiled due to an earlier failure in fail-fast mode.
+    pub skipped: Vec<CompilableObject>,
+}
+
+impl Sum for CompileResult {
+    fn sum<I>(iter: I) -> Self
+    where
+        I: Iterator<Item = Self>,
+    {
+        iter.fold(Self::new(PathBuf::new()), |mut acc, mut object| {
+            acc.path = object.path;
+            acc.compiled.append(&mut object.compiled);
+            acc.failed.append(&mut object.failed);
+            acc.skipped.append(&mut object.skipped);
+
+            acc
+        })
+    }
+}
+
+impl CompileResult {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            compiled: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// A struct for handling the parameters for the compilation environment.
+///
+/// This data structure is made for abstracting the compilation process making it as a separate component.
+/// Ideally, this is used for compiling a subject and its notes/master note.
+pub struct CompilationEnvironment {
+    pub path: PathBuf,
+    pub subject: Subject,
+    pub shelf: Shelf,
+    pub compilables: Vec<CompilableObject>,
+    pub compiler: Compiler,
+    output_dir: Option<PathBuf>,
+    pdf_dir: Option<PathBuf>,
+    thread_count: i16,
+    fail_fast: bool,
+}
+
+impl CompilationEnvironment {
+    /// Create a new compilation environment instance for the subject in the shelf.
+    pub fn new(
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> Self {
+        Self {
+            path: subject.path_in_shelf(shelf),
+            subject: subject.clone(),
+            shelf: shelf.clone(),
+            compilables: vec![],
+            compiler: Compiler::default(),
+            output_dir: None,
+            pdf_dir: None,
+            thread_count: 1,
+            fail_fast: false,
+        }
+    }
+
+    /// Set the notes to be compiled.
+    pub fn compilables(
+        &mut self,
+        notes: Vec<CompilableObject>,
+    ) -> &mut Self {
+        self.compilables = notes;
+        self
+    }
+
+    /// Set the compiler (i.e., a preset or a raw command).
+    pub fn compiler<C>(
+        &mut self,
+        compiler: C,
+    ) -> &mut Self
+    where
+        C: Into<Compiler>,
+    {
+        self.compiler = compiler.into();
+        self
+    }
+
+    /// Set the thread count.
+    pub fn thread_count(
+        &mut self,
+        thread_count: i16,
+    ) -> &mut Self {
+        self.thread_count = thread_count;
+        self
+    }
+
+    /// Set whether to stop starting new compilation jobs after the first failure.
+    pub fn fail_fast(
+        &mut self,
+        fail_fast: bool,
+    ) -> &mut Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Set the output directory (relative to the shelf) for the build artifacts.
+    pub fn output_dir<P>(
+        &mut self,
+        output_dir: P,
+    ) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.output_dir = Some(output_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory (relative to the shelf) where the resulting PDFs are copied into.
+    pub fn pdf_dir<P>(
+        &mut self,
+        pdf_dir: P,
+    ) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.pdf_dir = Some(pdf_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the output directories from the configuration of the shelf and the subject.
+    /// The subject configuration has the higher precedence.
+    pub fn output_dirs_from_config(
+        &mut self,
+        shelf_config: &ShelfConfig,
+        subject_config: &SubjectConfig,
+    ) -> &mut Self {
+        if let Some(output_dir) = subject_config
+            .output_dir
+            .as_ref()
+            .or(shelf_config.output_dir.as_ref())
+        {
+            self.output_dir(output_dir);
+        }
+
+        if let Some(pdf_dir) = subject_config
+            .pdf_dir
+            .as_ref()
+            .or(shelf_config.pdf_dir.as_ref())
+        {
+            self.pdf_dir(pdf_dir);
+        }
+
+        self
+    }
+
+    /// Returns the directory where the build artifacts are placed.
+    /// If there is no output directory, it is the subject folder.
+    pub fn build_dir(&self) -> PathBuf {
+        match &self.output_dir {
+            Some(output_dir) => self.shelf.path().join(output_dir).join(self.subject.path()),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Returns the path where the resulting PDF of the compilable is copied into.
+    /// The file name is made with the subject path and the stem of the compilable to prevent name clashes.
+    pub fn pdf_path(
+        &self,
+        compilable: &dyn Compilable,
+    ) -> Option<PathBuf> {
+        let pdf_dir = self.pdf_dir.as_ref()?;
+        let mut components: Vec<String> = self
+            .subject
+            .path()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        components.push(compilable.stem());
+
+        Some(
+            self.shelf
+                .path()
+                .join(pdf_dir)
+                .join(format!("{}.pdf", components.join("-"))),
+        )
+    }
+
+    /// Returns the path of the resulting PDF of the compilable.
+    /// It is the copy in the PDF directory if there's any, otherwise the PDF in the build directory.
+    pub fn output_pdf_path(
+        &self,
+        compilable: &dyn Compilable,
+    ) -> PathBuf {
+        self.pdf_path(compilable)
+            .unwrap_or_else(|| self.build_dir().join(format!("{}.pdf", compilable.stem())))
+    }
+
+    /// Returns the object used for rendering the command of the compilable.
+    pub fn command_object(
+        &self,
+        profile: &Profile,
+        compilable: &dyn Compilable,
+    ) -> toml::Value {
+        // The commands are executed in the subject folder.
+        let output_dir = match helpers::fs::relative_path_from(self.build_dir(), &self.path) {
+            Some(path) if !path.as_os_str().is_empty() => path,
+            _ => PathBuf::from("."),
+        };
+        let stem = compilable.stem();
+        let artifacts: Vec<String> = self
+            .compiler
+            .artifacts()
+            .iter()
+            .map(|extension| format!("{}.{}", stem, extension))
+            .collect();
+
+        let mut object = toml::Value::from(HashMap::<String, toml::Value>::new());
+        modify_toml_table! {object,
+            ("profile", Object::data(profile)),
+            ("subject", ShelfData::data(&self.subject, &self.shelf)),
+            ("note", compilable.data(&self.subject, &self.shelf)),
+            ("shelf", Object::data(&self.shelf)),
+            ("output_dir", output_dir),
+            ("artifacts", artifacts)
+        };
+
+        object
+    }
+
+    /// Executes the compilation steps in order for the compilable.
+    /// The templates of each step is rendered with the templates from the profile and the command object.
+    ///
+    /// If a step fails, it returns the failed step along with the reason.
+    fn compile_steps(
+        &self,
+        steps: &[CompileStep],
+        profile: &Profile,
+        compilable: &dyn Compilable,
+        observer: &dyn CompileObserver,
+    ) -> std::result::Result<(), StepFailure> {
+        let object = self.command_object(profile, compilable);
+        let render = |template: &str| {
+            profile
+                .template_registry()
+                .render_raw_template(template, &object)
+        };
+
+        for (index, step) in steps.iter().enumerate() {
+            let failure = |command: &str, error: Error| StepFailure {
+                index,
+                command: command.to_string(),
+                error,
+            };
+
+            let command_string = render(&step.command).map_err(|e| failure(&step.command, e))?;
+
+            if let Some(file) = &step.if_exists {
+                let file = render(file).map_err(|e| failure(&command_string, e))?;
+
+                if !self.build_dir().join(file).exists() {
+                    continue;
+                }
+            }
+
+            let mut run_count = 0;
+            loop {
+                let status = self
+                    .run_command(&command_string, compilable, observer)
+                    .map_err(|e| failure(&command_string, e))?;
+                if !status.success() {
+                    return Err(failure(&command_string, Error::ProcessError(status)));
+                }
+
+                let rerun = match &step.rerun_while {
+                    Some(condition) if run_count < step.max_reruns => {
+                        let file =
+                            render(&condition.file).map_err(|e| failure(&command_string, e))?;
+
+                        fs::read_to_string(self.build_dir().join(file))
+                            .map(|content| content.contains(&condition.contains))
+                            .unwrap_or(false)
+                    }
+                    _ => false,
+                };
+
+                if !rerun {
+                    break;
+                }
+
+                run_count += 1;
+            }
+        }
+
+        self.copy_pdf(compilable).map_err(|error| StepFailure {
+            index: steps.len(),
+            command: String::from("copy the resulting PDF"),
+            error,
+        })
+    }
+
+    /// Executes the command in the subject folder.
+    /// Each line of the output is sent to the observer as it comes.
+    fn run_command(
+        &self,
+        command: &str,
+        compilable: &dyn Compilable,
+        observer: &dyn CompileObserver,
+    ) -> Result<process::ExitStatus> {
+        let mut child = str_as_cmd(command)
+            .current_dir(&self.path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::IoError)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut readers = vec![];
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(send_lines(stdout, sender.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(send_lines(stderr, sender.clone()));
+        }
+        drop(sender);
+
+        // The receiver stops once all of the senders from the readers are dropped.
+        for line in receiver {
+            observer.output_line(compilable, &line);
+        }
+
+        for reader in readers {
+            let _ = reader.join();
+        }
+
+        child.wait().map_err(Error::IoError)
+    }
+
+    /// Records the state of the compilation in the index of the shelf, if it's opened.
+    fn record_compile_state(
+        &self,
+        compilable: &dyn Compilable,
+        succeeded: bool,
+    ) {
+        let data = compilable.data(&self.subject, &self.shelf);
+        let file = match data.get("file").and_then(toml::Value::as_str) {
+            Some(file) => self.subject.path().join(file),
+            None => return,
+        };
+
+        if let Some(mut index) = self.shelf.index() {
+            index.set_compile_state(file, succeeded);
+        }
+    }
+
+    /// Copies the resulting PDF of the compilable into the PDF directory, if there's any.
+    fn copy_pdf(
+        &self,
+        compilable: &dyn Compilable,
+    ) -> Result<()> {
+        if let Some(dst) = self.pdf_path(compilable) {
+            let src = self.build_dir().join(format!("{}.pdf", compilable.stem()));
+
+            fs::copy(&src, &dst).map_err(Error::IoError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes the compilation process.
+    /// The command is rendered with the templates and the data from the given profile.
+    /// This also consume the struct.
+    pub fn compile(
+        self,
+        profile: &Profile,
+    ) -> Result<CompileResult> {
+        self.compile_with_observer(profile, &NoopObserver)
+    }
+
+    /// Executes the compilation process while sending the progress to the observer.
+    /// This also consume the struct.
+    pub fn compile_with_observer(
+        self,
+        profile: &Profile,
+        observer: &dyn CompileObserver,
+    ) -> Result<CompileResult> {
+        let thread_count = self.thread_count;
+        let mut compile_results =
+            compile_environments(vec![self], profile, observer, thread_count)?;
+
+        Ok(compile_results.remove(0))
+    }
+
+    /// Creates the output directories of the environment.
+    fn create_output_dirs(&self) -> Result<()> {
+        fs::create_dir_all(self.build_dir()).map_err(Error::IoError)?;
+        if let Some(pdf_dir) = &self.pdf_dir {
+            fs::create_dir_all(self.shelf.path().join(pdf_dir)).map_err(Error::IoError)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of a single compilation job.
+enum JobOutcome {
+    Compiled(CompilableObject),
+    Failed(CompileFailure),
+    Skipped(CompilableObject),
+}
+
+/// Executes the compilation process of multiple environments in a shared thread pool.
+/// This is useful for compiling the notes from multiple subjects (e.g., their master notes) in parallel.
+///
+/// The thread count of the environments are ignored in favor of the given thread count.
+/// The results are in the same order as the given environments.
+pub fn compile_environments(
+    mut envs: Vec<CompilationEnvironment>,
+    profile: &Profile,
+    observer: &dyn CompileObserver,
+    thread_count: i16,
+) -> Result<Vec<CompileResult>> {
+    let mut jobs: Vec<(usize, CompilableObject)> = vec![];
+    for (index, env) in envs.iter_mut().enumerate() {
+        env.create_output_dirs()?;
+
+        let compilables = mem::take(&mut env.compilables);
+        jobs.extend(
+            compilables
+                .into_iter()
+                .map(|compilable| (index, compilable)),
+        );
+    }
+
+    let envs = &envs;
+    let steps: Vec<Vec<CompileStep>> = envs.iter().map(|env| env.compiler.steps()).collect();
+    let has_failed = AtomicBool::new(false);
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count.max(1) as usize)
+        .build()
+        .map_err(|e| Error::ThreadPoolError(e.to_string()))?;
+
+    let outcomes: Vec<(usize, JobOutcome)> = thread_pool.install(|| {
+        jobs.into_par_iter()
+            .map(|(index, compilable)| {
+                let env = &envs[index];
+                if env.fail_fast && has_failed.load(Ordering::SeqCst) {
+                    return (index, JobOutcome::Skipped(compilable));
+                }
+
+                observer.job_started(compilable.as_ref());
+
+                let outcome = match env.compile_steps(
+                    &steps[index],
+                    profile,
+                    compilable.as_ref(),
+                    observer,
+                ) {
+                    Ok(()) => {
+                        env.record_compile_state(compilable.as_ref(), true);
+                        observer.job_finished(compilable.as_ref());
+                        JobOutcome::Compiled(compilable)
+                    }
+                    Err(step) => {
+                        env.record_compile_state(compilable.as_ref(), false);
+                        has_failed.store(true, Ordering::SeqCst);
+                        observer.job_failed(compilable.as_ref(), &step);
+                        JobOutcome::Failed(CompileFailure { compilable, step })
+                    }
+                };
+
+                (index, outcome)
+            })
+            .collect()
+    });
+
+    let mut compile_results: Vec<CompileResult> = envs
+        .iter()
+        .map(|env| CompileResult::new(env.path.clone()))
+        .collect();
+    for (index, outcome) in outcomes {
+        let compile_result = &mut compile_results[index];
+
+        match outcome {
+            JobOutcome::Compiled(compilable) => compile_result.compiled.push(compilable),
+            JobOutcome::Failed(failure) => compile_result.failed.push(failure),
+            JobOutcome::Skipped(compilable) => compile_result.skipped.push(compilable),
+        }
+    }
+
+    Ok(compile_results)
+}
+
+/// Reads the lines from the given reader in a separate thread and sends them through the channel.
+fn send_lines<R>(
+    reader: R,
+    sender: mpsc::Sender<String>,
+) -> thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            match line {
+                Ok(line) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+                Err(_e) => break,
+            }
+        }
+    })
+}
+
+/// Converts the string into a command struct.
+/// The arguments are separated by whitespace.
+fn str_as_cmd<S>(string: S) -> process::Command
+where
+    S: AsRef<str>,
+{
+    let string = string.as_ref();
+    let mut command_iter = string.split_whitespace();
+
+    let mut command_process = process::Command::new(command_iter.next().unwrap_or_default());
+    for arg in command_iter {
+        command_process.arg(arg);
+    }
+
+    command_process
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CompileCommand, RerunCondition};
+    use std::sync::Mutex;
+    use tempfile;
+
+    /// An observer that records all of the events.
+    struct RecordingObserver(Mutex<Vec<String>>);
+
+    impl CompileObserver for RecordingObserver {
+        fn job_started(
+            &self,
+            compilable: &dyn Compilable,
+        ) {
+            self.0.lock().unwrap().push(format!("start {}", compilable));
+        }
+
+        fn job_finished(
+            &self,
+            compilable: &dyn Compilable,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("finish {}", compilable));
+        }
+
+        fn job_failed(
+            &self,
+            compilable: &dyn Compilable,
+            failure: &StepFailure,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("fail {} {}", compilable, failure.index));
+        }
+
+        fn output_line(
+            &self,
+            compilable: &dyn Compilable,
+            line: &str,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("output {} {}", compilable, line));
+        }
+    }
+
+    fn tmp_env(
+        notes: &[&str]
+    ) -> Result<(tempfile::TempDir, Profile<'static>, CompilationEnvironment)> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+
+        let mut compilables: Vec<CompilableObject> = vec![];
+        for note in notes {
+            let note = Note::new(note);
+            note.export((&subject, &shelf))?;
+            compilables.push(Box::new(note));
+        }
+
+        let mut env = CompilationEnvironment::new(&subject, &shelf);
+        env.compilables(compilables);
+
+        Ok((tmp_dir, Profile::default(), env))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_with_observer() -> Result<()> {
+        let (_tmp_dir, profile, mut env) = tmp_env(&["Introduction to Limits", "Taylor Series"])?;
+        env.compiler(CompileCommand::Steps(vec![
+            CompileStep::new("echo {{note.stem}}"),
+            CompileStep::new("test {{note.stem}} = taylor-series"),
+        ]))
+        .thread_count(2);
+
+        let observer = RecordingObserver(Mutex::new(vec![]));
+        let result = env.compile_with_observer(&profile, &observer)?;
+        let events = observer.0.into_inner().unwrap();
+
+        assert_eq!(result.compiled.len(), 1);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].step.index, 1);
+        assert!(events.contains(&"start Taylor Series".to_string()));
+        assert!(events.contains(&"finish Taylor Series".to_string()));
+        assert!(events.contains(&"fail Introduction to Limits 1".to_string()));
+        assert!(events.contains(&"output Taylor Series taylor-series".to_string()));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_with_legacy_note_variable() -> Result<()> {
+        let (tmp_dir, profile, mut env) = tmp_env(&["Taylor Series"])?;
+        env.compiler(CompileCommand::from("cp {{ note}} {{note }}.bak"));
+
+        let result = env.compile(&profile)?;
+
+        assert_eq!(result.compiled.len(), 1);
+        assert!(tmp_dir
+            .path()
+            .join("calculus/taylor-series.tex.bak")
+            .is_file());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_with_fail_fast() -> Result<()> {
+        let (_tmp_dir, profile, mut env) =
+            tmp_env(&["Introduction to Limits", "Taylor Series", "Power Series"])?;
+        env.compiler(CompileCommand::from("false"))
+            .thread_count(1)
+            .fail_fast(true);
+
+        let result = env.compile(&profile)?;
+
+        assert_eq!(result.compiled.len(), 0);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.skipped.len(), 2);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_of_multiple_environments() -> Result<()> {
+        let (_tmp_dir, profile, mut env) = tmp_env(&["Introduction to Limits"])?;
+        env.compiler(CompileCommand::from("true"));
+
+        let algebra = Subject::new("Algebra");
+        algebra.export(&env.shelf)?;
+        let mut algebra_env = CompilationEnvironment::new(&algebra, &env.shelf);
+        algebra_env
+            .compilables(vec![
+                Box::new(MasterNote::new(algebra.clone())),
+                Box::new(Note::new("Groups")),
+            ])
+            .compiler(CompileCommand::from("false"));
+
+        let results = compile_environments(vec![env, algebra_env], &profile, &NoopObserver, 2)?;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].compiled.len(), 1);
+        assert_eq!(results[1].failed.len(), 2);
+        assert!(results[1].path.ends_with("algebra"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_with_output_dirs() -> Result<()> {
+        let (tmp_dir, profile, mut env) = tmp_env(&["Taylor Series"])?;
+        env.compiler(CompileCommand::from(
+            "touch {{output_dir}}/{{note.stem}}.pdf",
+        ))
+        .output_dir("build")
+        .pdf_dir("pdf");
+
+        let result = env.compile(&profile)?;
+
+        assert_eq!(result.compiled.len(), 1);
+        assert!(tmp_dir
+            .path()
+            .join("build/calculus/taylor-series.pdf")
+            .is_file());
+        assert!(tmp_dir
+            .path()
+            .join("pdf/calculus-taylor-series.pdf")
+            .is_file());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compilation_steps_with_output_dirs() -> Result<()> {
+        let (_tmp_dir, profile, mut env) = tmp_env(&["Taylor Series"])?;
+        let mut conditional_step = CompileStep::new("echo biber");
+        conditional_step.if_exists = Some("{{note.stem}}.bcf".to_string());
+        let mut rerun_step = CompileStep::new("echo rerun");
+        rerun_step.rerun_while = Some(RerunCondition {
+            file: "{{note.stem}}.log".to_string(),
+            contains: String::new(),
+        });
+        rerun_step.max_reruns = 2;
+        env.compiler(CompileCommand::Steps(vec![
+            CompileStep::new(
+                "touch {{output_dir}}/{{note.stem}}.bcf {{output_dir}}/{{note.stem}}.log",
+            ),
+            conditional_step,
+            rerun_step,
+        ]))
+        .output_dir("build");
+
+        let observer = RecordingObserver(Mutex::new(vec![]));
+        let result = env.compile_with_observer(&profile, &observer)?;
+        let events = observer.0.into_inner().unwrap();
+
+        // The files of the conditions are found in the build directory instead of the subject folder.
+        assert_eq!(result.compiled.len(), 1);
+        assert!(events.contains(&"output Taylor Series biber".to_string()));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| *event == "output Taylor Series rerun")
+                .count(),
+            3
+        );
+
+        Ok(())
+    }
+}
@@ -1,41 +1,359 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use toml::Value;
 
 use crate::consts;
 use crate::error::Error;
+use crate::hooks::{HookCommand, HookEvent};
+use crate::note::TagFilter;
+use crate::slug::SlugStrategy;
+use crate::templates::TemplateEscape;
 
-const DEFAULT_FILES: &str = "*.tex";
-const DEFAULT_CMD: &str = "latexmk -pdf {{note}}";
+const DEFAULT_EXTENSION: &str = "tex";
+const TYPST_EXTENSION: &str = "typ";
 const DEFAULT_NAME: &str = "New Student";
+const DEFAULT_MAX_RERUNS: u8 = 3;
 
-/// The configuration of a subject.
+/// The compilation command of a subject.
+///
+/// It can be a single command or an ordered list of steps.
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct SubjectConfig {
-    #[serde(rename = "_files", default = "default_files")]
-    pub files: Vec<String>,
+#[serde(untagged)]
+pub enum CompileCommand {
+    Single(String),
+    Steps(Vec<CompileStep>),
+}
 
-    #[serde(default = "default_cmd")]
-    pub command: String,
+impl From<String> for CompileCommand {
+    fn from(command: String) -> Self {
+        CompileCommand::Single(command)
+    }
+}
 
-    #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
+impl From<&str> for CompileCommand {
+    fn from(command: &str) -> Self {
+        CompileCommand::Single(command.to_string())
+    }
+}
+
+impl CompileCommand {
+    /// Returns the command as a list of steps.
+    pub fn steps(&self) -> Vec<CompileStep> {
+        match self {
+            CompileCommand::Single(command) => vec![CompileStep::new(command)],
+            CompileCommand::Steps(steps) => steps.clone(),
+        }
+    }
+}
+
+/// The built-in compiler presets.
+///
+/// Each preset expands into a command with the output directory, SyncTeX, and non-interactive flags.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Latexmk,
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+    Tectonic,
+    Typst,
+}
+
+impl FromStr for Engine {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::Value::String(s.to_string())
+            .try_into()
+            .map_err(Error::TomlValueError)
+    }
+}
+
+impl Engine {
+    /// Returns the command of the preset.
+    pub fn command(&self) -> CompileCommand {
+        match self {
+            Engine::Latexmk => CompileCommand::Single(
+                "latexmk -pdf -interaction=nonstopmode -synctex=1 -outdir={{output_dir}} {{note.file}}"
+                    .to_string(),
+            ),
+            Engine::Pdflatex => Self::tex_engine_command("pdflatex"),
+            Engine::Xelatex => Self::tex_engine_command("xelatex"),
+            Engine::Lualatex => Self::tex_engine_command("lualatex"),
+            Engine::Tectonic => CompileCommand::Single(
+                "tectonic --synctex --keep-logs --outdir={{output_dir}} {{note.file}}".to_string(),
+            ),
+            Engine::Typst => CompileCommand::Single(
+                "typst compile {{note.file}} {{output_dir}}/{{note.stem}}.pdf".to_string(),
+            ),
+        }
+    }
+
+    /// Returns the file extensions of the artifacts produced by the preset.
+    /// The artifacts are placed in the output directory with the note stem as the file name.
+    pub fn artifacts(&self) -> Vec<&'static str> {
+        match self {
+            Engine::Latexmk => vec!["pdf", "synctex.gz", "log", "aux", "fls", "fdb_latexmk"],
+            Engine::Pdflatex | Engine::Xelatex | Engine::Lualatex => {
+                vec!["pdf", "synctex.gz", "log", "aux"]
+            }
+            Engine::Tectonic => vec!["pdf", "synctex.gz", "log"],
+            Engine::Typst => vec!["pdf"],
+        }
+    }
+
+    /// Returns the file extension of the documents compiled by the preset.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Engine::Typst => TYPST_EXTENSION,
+            _ => DEFAULT_EXTENSION,
+        }
+    }
+
+    /// Returns the preset for the documents with the given file extension, if there's any.
+    /// The LaTeX documents have no preset since they can be compiled by any of the TeX engines.
+    pub fn from_extension<S: AsRef<str>>(extension: S) -> Option<Self> {
+        match extension.as_ref() {
+            TYPST_EXTENSION => Some(Engine::Typst),
+            _ => None,
+        }
+    }
+
+    /// The common command of the TeX engines.
+    /// Since they do not rerun by themselves, the step is rerun as long as the log says so.
+    fn tex_engine_command(program: &str) -> CompileCommand {
+        let mut step = CompileStep::new(format!(
+            "{} -interaction=nonstopmode -halt-on-error -synctex=1 -output-directory={{{{output_dir}}}} {{{{note.file}}}}",
+            program
+        ));
+        step.rerun_while = Some(RerunCondition {
+            file: "{{note.stem}}.log".to_string(),
+            contains: "Rerun to get".to_string(),
+        });
+
+        CompileCommand::Steps(vec![step])
+    }
 }
 
-impl Default for SubjectConfig {
+/// The resulting compiler from the configuration.
+/// It is either a built-in preset or a raw command.
+#[derive(Clone, Debug)]
+pub enum Compiler {
+    Engine(Engine),
+    Command(CompileCommand),
+}
+
+impl Default for Compiler {
     fn default() -> Self {
+        Compiler::Engine(Engine::default())
+    }
+}
+
+impl From<Engine> for Compiler {
+    fn from(engine: Engine) -> Self {
+        Compiler::Engine(engine)
+    }
+}
+
+impl From<CompileCommand> for Compiler {
+    fn from(command: CompileCommand) -> Self {
+        Compiler::Command(command)
+    }
+}
+
+impl From<String> for Compiler {
+    fn from(command: String) -> Self {
+        Compiler::Command(CompileCommand::from(command))
+    }
+}
+
+impl Compiler {
+    /// Returns the command of the compiler as a list of steps.
+    pub fn steps(&self) -> Vec<CompileStep> {
+        match self {
+            Compiler::Engine(engine) => engine.command().steps(),
+            Compiler::Command(command) => command.steps(),
+        }
+    }
+
+    /// Returns the file extensions of the artifacts produced by the compiler.
+    /// Raw commands are assumed to produce a PDF.
+    pub fn artifacts(&self) -> Vec<&'static str> {
+        match self {
+            Compiler::Engine(engine) => engine.artifacts(),
+            Compiler::Command(_) => vec!["pdf"],
+        }
+    }
+}
+
+/// A step of the compilation process.
+///
+/// All of the string fields are Handlebars templates rendered with the same object as the command.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "CompileStepValue")]
+pub struct CompileStep {
+    /// The command to be executed.
+    pub command: String,
+
+    /// Only execute the step if the file exists (e.g., `{{note.stem}}.bcf`).
+    /// A relative path is resolved against the build directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub if_exists: Option<String>,
+
+    /// Rerun the step while the condition is met.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerun_while: Option<RerunCondition>,
+
+    /// The maximum number of times the step is rerun.
+    pub max_reruns: u8,
+}
+
+impl CompileStep {
+    /// Creates a step with the given command and no conditions.
+    pub fn new<S>(command: S) -> Self
+    where
+        S: AsRef<str>,
+    {
         Self {
-            files: vec![DEFAULT_FILES.to_string()],
-            command: DEFAULT_CMD.to_string(),
-            extra: HashMap::new(),
+            command: command.as_ref().to_string(),
+            if_exists: None,
+            rerun_while: None,
+            max_reruns: DEFAULT_MAX_RERUNS,
+        }
+    }
+}
+
+/// A condition for rerunning a compilation step.
+/// The condition is met if the file contains the given string.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RerunCondition {
+    /// The file to be checked (e.g., `{{note.stem}}.log`).
+    /// A relative path is resolved against the build directory.
+    pub file: String,
+
+    /// The string to be searched in the file (e.g., `Rerun to get`).
+    pub contains: String,
+}
+
+/// A step can be set either as a string or a table.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CompileStepValue {
+    Command(String),
+    Step {
+        command: String,
+        if_exists: Option<String>,
+        rerun_while: Option<RerunCondition>,
+        #[serde(default = "default_max_reruns")]
+        max_reruns: u8,
+    },
+}
+
+impl From<CompileStepValue> for CompileStep {
+    fn from(value: CompileStepValue) -> Self {
+        match value {
+            CompileStepValue::Command(command) => Self::new(command),
+            CompileStepValue::Step {
+                command,
+                if_exists,
+                rerun_while,
+                max_reruns,
+            } => Self {
+                command,
+                if_exists,
+                rerun_while,
+                max_reruns,
+            },
         }
     }
 }
 
+/// The sorting methods of the notes in a master note.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteSort {
+    /// Sort the notes by their title.
+    #[default]
+    Title,
+
+    /// Sort the notes by the modification time of their file.
+    Modified,
+
+    /// Sort the notes by the creation time of their file.
+    /// If the filesystem does not support it, the modification time is used instead.
+    Created,
+}
+
+impl FromStr for NoteSort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::Value::String(s.to_string())
+            .try_into()
+            .map_err(Error::TomlValueError)
+    }
+}
+
+/// The configuration of a subject.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SubjectConfig {
+    /// The file globs of the notes.
+    /// By default, it matches all of the files with the extension of the subject.
+    #[serde(rename = "_files", skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+
+    /// The file extension of the notes (e.g., `tex`, `md`, `typ`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+
+    /// The raw compilation command.
+    /// It has a higher precedence than the engine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<CompileCommand>,
+
+    /// The compiler preset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<Engine>,
+
+    /// The directory (relative to the shelf) where the build artifacts are placed.
+    /// It overrides the value from the shelf.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+
+    /// The directory (relative to the shelf) where the resulting PDFs are copied into.
+    /// It overrides the value from the shelf.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf_dir: Option<PathBuf>,
+
+    /// The sorting method of the notes in the master note.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<NoteSort>,
+
+    /// An explicit order of the notes (by their title or file name) in the master note.
+    /// It takes precedence over the sorting method.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+
+    /// Excludes the notes that are not in the explicit order from the master note.
+    /// Otherwise, they are appended after the ordered notes.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub exclude_unordered: bool,
+
+    /// The named master notes of the subject.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub masters: HashMap<String, MasterConfig>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
 impl TryFrom<&Path> for SubjectConfig {
     type Error = Error;
 
@@ -60,14 +378,166 @@ impl SubjectConfig {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns the file extension of the notes of the subject.
+    /// Without an explicit extension, it is the file extension of the documents compiled by the engine.
+    pub fn extension(&self) -> String {
+        match (&self.extension, self.engine) {
+            (Some(extension), _) => extension.clone(),
+            (None, Some(engine)) => engine.extension().to_string(),
+            (None, None) => DEFAULT_EXTENSION.to_string(),
+        }
+    }
+
+    /// Returns the file globs of the notes of the subject.
+    pub fn file_globs(&self) -> Vec<String> {
+        match &self.files {
+            Some(files) => files.clone(),
+            None => vec![format!("*.{}", self.extension())],
+        }
+    }
+
+    /// Returns the compiler of the subject.
+    ///
+    /// The compiler is resolved in the following order: the command and the engine of the subject,
+    /// the command and the engine of the profile, and the default engine.
+    pub fn compiler(
+        &self,
+        profile_config: &ProfileConfig,
+    ) -> Compiler {
+        if let Some(command) = &self.command {
+            return Compiler::Command(command.clone());
+        }
+
+        if let Some(engine) = self.engine {
+            return Compiler::Engine(engine);
+        }
+
+        // The profile compiler is assumed to be for LaTeX documents.
+        if let Some(engine) = Engine::from_extension(self.extension()) {
+            return Compiler::Engine(engine);
+        }
+
+        profile_config.compiler()
+    }
+
+    /// Returns the configuration of the master note with the given name.
+    /// The unset values of a named master note fall back to the values of the subject
+    /// while the default master note (i.e., no name) is made entirely from the subject.
+    ///
+    /// It will return `None` if the subject has no master note with the given name.
+    pub fn master(
+        &self,
+        name: Option<&str>,
+    ) -> Option<MasterConfig> {
+        let master = match name {
+            Some(name) => self.masters.get(name)?.clone(),
+            None => MasterConfig::default(),
+        };
+
+        Some(MasterConfig {
+            files: master.files.or_else(|| Some(self.file_globs())),
+            extension: master.extension.or_else(|| Some(self.extension())),
+            sort: master.sort.or(self.sort),
+            order: match master.order.is_empty() {
+                true => self.order.clone(),
+                false => master.order,
+            },
+            exclude_unordered: master.exclude_unordered.or(Some(self.exclude_unordered)),
+            ..master
+        })
+    }
+}
+
+/// The configuration of a named master note of a subject.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MasterConfig {
+    /// The file globs of the notes to be included.
+    #[serde(rename = "_files", skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+
+    /// The name of the template from the profile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// The file name of the resulting master note.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+
+    /// The file extension of the resulting master note.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<NoteSort>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_unordered: Option<bool>,
+
+    /// The tags of the notes to be included (`tags`) and excluded (`exclude_tags`).
+    #[serde(flatten)]
+    pub tag_filter: TagFilter,
 }
 
-fn default_files() -> Vec<String> {
-    vec![DEFAULT_FILES.to_string()]
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
-fn default_cmd() -> String {
-    DEFAULT_CMD.to_string()
+/// The configuration of a shelf.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ShelfConfig {
+    /// The directory (relative to the shelf) where the build artifacts are placed.
+    /// The artifacts of each subject is placed in a folder mirroring the subject path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+
+    /// The directory (relative to the shelf) where the resulting PDFs are copied into.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf_dir: Option<PathBuf>,
+
+    /// The strategy for creating the file names of the notes and subjects from their titles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<SlugStrategy>,
+
+    /// The hook commands of the shelf run after the hooks of the profile.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<HookEvent, HookCommand>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl TryFrom<&Path> for ShelfConfig {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let content = fs::read_to_string(path).map_err(Error::IoError)?;
+
+        toml::from_str(&content).map_err(Error::TomlValueError)
+    }
+}
+
+impl TryFrom<PathBuf> for ShelfConfig {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        let content = fs::read_to_string(path).map_err(Error::IoError)?;
+
+        toml::from_str(&content).map_err(Error::TomlValueError)
+    }
+}
+
+impl ShelfConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn default_max_reruns() -> u8 {
+    DEFAULT_MAX_RERUNS
 }
 
 fn default_name() -> String {
@@ -87,6 +557,27 @@ pub struct ProfileConfig {
     #[serde(default = "default_version")]
     version: String,
 
+    /// The default raw compilation command for all subjects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<CompileCommand>,
+
+    /// The default compiler preset for all subjects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<Engine>,
+
+    /// The escaping applied to the values in the templates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape: Option<TemplateEscape>,
+
+    /// The hook commands run before and after the operations on the shelf.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<HookEvent, HookCommand>,
+
+    /// The paths of the shelves allowed to run their own hooks.
+    /// The hooks of the other shelves are ignored since they can come from anywhere (e.g., a cloned shelf).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_shelves: Vec<PathBuf>,
+
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
@@ -96,6 +587,11 @@ impl Default for ProfileConfig {
         Self {
             name: default_name(),
             version: default_version(),
+            command: None,
+            engine: None,
+            escape: None,
+            hooks: BTreeMap::new(),
+            trusted_shelves: vec![],
             extra: HashMap::new(),
         }
     }
@@ -125,4 +621,147 @@ impl ProfileConfig {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns the default compiler of the profile.
+    pub fn compiler(&self) -> Compiler {
+        match (&self.command, self.engine) {
+            (Some(command), _) => Compiler::Command(command.clone()),
+            (None, Some(engine)) => Compiler::Engine(engine),
+            (None, None) => Compiler::default(),
+        }
+    }
+
+    /// Checks if the shelf at the path is one of the trusted shelves of the profile.
+    /// The paths are compared after resolving them so the symbolic links and relative paths still match.
+    pub fn trusts_shelf<P: AsRef<Path>>(
+        &self,
+        shelf_path: P,
+    ) -> bool {
+        let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_e| path.to_path_buf());
+        let shelf_path = resolve(shelf_path.as_ref());
+
+        self.trusted_shelves
+            .iter()
+            .any(|trusted_shelf| resolve(trusted_shelf) == shelf_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_command_config() {
+        let config: SubjectConfig = toml::from_str("command = 'pdflatex {{note.file}}'").unwrap();
+        let steps = config.compiler(&ProfileConfig::new()).steps();
+
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].command, "pdflatex {{note.file}}".to_string());
+        assert!(steps[0].if_exists.is_none());
+        assert!(steps[0].rerun_while.is_none());
+    }
+
+    #[test]
+    fn multiple_steps_config() {
+        let config: SubjectConfig = toml::from_str(
+            r#"
+            command = [
+                "pdflatex {{note.file}}",
+                { command = "biber {{note.stem}}", if_exists = "{{note.stem}}.bcf" },
+                { command = "pdflatex {{note.file}}", rerun_while = { file = "{{note.stem}}.log", contains = "Rerun" }, max_reruns = 5 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let steps = config.compiler(&ProfileConfig::new()).steps();
+
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].max_reruns, DEFAULT_MAX_RERUNS);
+        assert_eq!(steps[1].if_exists, Some("{{note.stem}}.bcf".to_string()));
+        assert_eq!(steps[2].rerun_while.as_ref().unwrap().contains, "Rerun");
+        assert_eq!(steps[2].max_reruns, 5);
+
+        // The configuration should be able to be serialized back for the subject object.
+        assert!(toml::Value::try_from(config).is_ok());
+    }
+
+    #[test]
+    fn compiler_precedence() {
+        let mut profile_config = ProfileConfig::new();
+        let mut subject_config = SubjectConfig::new();
+
+        match subject_config.compiler(&profile_config) {
+            Compiler::Engine(engine) => assert_eq!(engine, Engine::Latexmk),
+            _ => panic!("The default compiler should be the latexmk preset."),
+        }
+
+        profile_config.engine = Some(Engine::Tectonic);
+        match subject_config.compiler(&profile_config) {
+            Compiler::Engine(engine) => assert_eq!(engine, Engine::Tectonic),
+            _ => panic!("The profile engine should be used."),
+        }
+
+        subject_config = toml::from_str("engine = 'xelatex'").unwrap();
+        match subject_config.compiler(&profile_config) {
+            Compiler::Engine(engine) => assert_eq!(engine, Engine::Xelatex),
+            _ => panic!("The subject engine should be used."),
+        }
+
+        subject_config.command = Some(CompileCommand::from("make"));
+        match subject_config.compiler(&profile_config) {
+            Compiler::Command(command) => assert_eq!(command.steps()[0].command, "make"),
+            _ => panic!("The raw command should have the highest precedence."),
+        }
+    }
+
+    #[test]
+    fn typst_subject_config() {
+        let mut profile_config = ProfileConfig::new();
+        profile_config.engine = Some(Engine::Tectonic);
+
+        let subject_config: SubjectConfig = toml::from_str("extension = 'typ'").unwrap();
+        assert_eq!(subject_config.file_globs(), vec!["*.typ".to_string()]);
+        match subject_config.compiler(&profile_config) {
+            Compiler::Engine(engine) => assert_eq!(engine, Engine::Typst),
+            _ => panic!("The Typst preset should be used for the Typst documents."),
+        }
+
+        let subject_config: SubjectConfig = toml::from_str("engine = 'typst'").unwrap();
+        assert_eq!(subject_config.extension(), "typ");
+        assert_eq!(
+            subject_config.master(None).unwrap().extension,
+            Some("typ".to_string())
+        );
+    }
+
+    #[test]
+    fn engine_from_str() {
+        assert_eq!(Engine::from_str("lualatex").unwrap(), Engine::Lualatex);
+        assert!(Engine::from_str("pdftex").is_err());
+    }
+
+    #[test]
+    fn engine_presets_are_non_interactive() {
+        for engine in &[
+            Engine::Latexmk,
+            Engine::Pdflatex,
+            Engine::Xelatex,
+            Engine::Lualatex,
+            Engine::Tectonic,
+            Engine::Typst,
+        ] {
+            let steps = engine.command().steps();
+            let command = &steps[0].command;
+
+            assert!(command.contains("{{output_dir}}"));
+            assert!(engine.artifacts().contains(&"pdf"));
+            if engine.extension() == "tex" {
+                assert!(command.contains("synctex"));
+            }
+        }
+
+        assert!(Engine::Pdflatex.command().steps()[0]
+            .command
+            .contains("-interaction=nonstopmode"));
+    }
 }
@@ -1,7 +1,7 @@
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub const MASTER_NOTE_TEMPLATE: &'static str = r"\documentclass[class=memoir, crop=false, oneside, 12pt]{standalone}
+pub const MASTER_NOTE_TEMPLATE: &str = r#"\documentclass[class=memoir, crop=false, oneside, 12pt]{standalone}
 
 % document metadata
 \author{ {{~profile.name~}} }
@@ -15,10 +15,26 @@ pub const MASTER_NOTE_TEMPLATE: &'static str = r"\documentclass[class=memoir, cr
 Note: {{this.title}}
 {{/each }}
 
+{{#*inline "master-part"}}
+{{#if (eq level 1)}}\chapter{ {{~subject.name~}} }{{else}}{{#if (eq level 2)}}\section{ {{~subject.name~}} }{{else}}\subsection{ {{~subject.name~}} }{{/if}}{{/if}}
+
+{{#each notes}}
+Note: {{this.title}}
+{{/each }}
+
+{{#each parts}}
+{{> master-part}}
+{{/each }}
+{{/inline}}
+
+{{#each master.parts}}
+{{> master-part}}
+{{/each }}
+
 \end{document}
-";
+"#;
 
-pub const NOTE_TEMPLATE: &'static str = r"\documentclass[class=memoir, crop=false, oneside, 14pt]{standalone}
+pub const NOTE_TEMPLATE: &str = r"{{{note.front_matter}}}\documentclass[class=memoir, crop=false, oneside, 14pt]{standalone}
 
 % document metadata
 \author{ {{~profile.name~}} }
@@ -31,3 +47,44 @@ Sample content.
 {{subject.name}}
 \end{document}
 ";
+
+pub const TYPST_MASTER_NOTE_TEMPLATE: &str = r#"#set document(title: "{{subject.name}}", author: "{{profile.name}}")
+#set heading(numbering: "1.")
+
+#align(center)[
+  #text(size: 20pt, weight: "bold")[{{subject.name}}] \
+  {{profile.name}} \
+  {{reldate}}
+]
+
+{{#each master.notes}}
+#include "{{this.file}}"
+{{/each }}
+
+{{#*inline "master-part"}}
+#heading(level: {{level}})[{{subject.name}}]
+
+{{#each notes}}
+#include "{{relpath this.path_in_shelf @root.subject._path_in_shelf}}"
+{{/each }}
+
+{{#each parts}}
+{{> master-part}}
+{{/each }}
+{{/inline}}
+
+{{#each master.parts}}
+{{> master-part}}
+{{/each }}
+"#;
+
+pub const TYPST_NOTE_TEMPLATE: &str = r#"{{{note.front_matter}}}#align(center)[
+  #text(size: 17pt, weight: "bold")[{{note.title}}] \
+  {{profile.name}} \
+  {{reldate}}
+]
+
+Sample content.
+
+{{subject.name}}
+"#;
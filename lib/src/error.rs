@@ -6,6 +6,8 @@ use std::process;
 
 use globwalk;
 use handlebars;
+use minijinja;
+use rhai;
 use toml;
 
 /// An enum for errors possible to happen in the Lanoma library.
@@ -35,9 +37,34 @@ pub enum Error {
     /// Given when a shell process has gone something wrong.
     ProcessError(process::ExitStatus),
 
+    /// Given when the master note has no notes to be included.
+    EmptyMasterNoteError(path::PathBuf),
+
+    /// Given when the front matter of the note is not a valid TOML.
+    FrontMatterError(path::PathBuf, toml::de::Error),
+
+    /// Given when the subject has no master note declared with the name.
+    UnknownMasterNoteError(path::PathBuf, String),
+
+    /// Given when the master note does not exist in the shelf.
+    MissingMasterNoteError(path::PathBuf),
+
+    /// Given when the title of an item has the same file name with a different item.
+    /// It contains the path of the file, the existing title, and the colliding title.
+    SlugCollisionError(path::PathBuf, String, String),
+
+    /// Given when some of the notes failed to compile with the number of failed notes.
+    CompilationError(usize),
+
+    /// Given when the threads for the compilation cannot be created with the message of the cause.
+    ThreadPoolError(String),
+
     /// Error when a part of the profile data is missing.
     MissingDataError(String),
 
+    /// Given when a required variable of the template is not given.
+    MissingTemplateVariableError(String, String),
+
     /// Related errors for the TOML library.
     TomlValueError(toml::de::Error),
     TomlSerializeError(toml::ser::Error),
@@ -46,6 +73,17 @@ pub enum Error {
     HandlebarsTemplateError(handlebars::TemplateError),
     HandlebarsTemplateFileError(handlebars::TemplateFileError),
     HandlebarsRenderError(handlebars::RenderError),
+    HandlebarsTemplateRenderError(handlebars::TemplateRenderError),
+
+    /// Related errors for Jinja.
+    JinjaTemplateError(minijinja::Error),
+    JinjaRenderError(minijinja::Error),
+
+    /// Given when the script of a helper from the profile is not valid.
+    ScriptHelperError(path::PathBuf, rhai::ParseError),
+
+    /// Given when a hook has failed with the name of the hook, its command, and the exit status.
+    HookError(String, String, process::ExitStatus),
 
     /// Given when the glob pattern is not recognizable.
     GlobParsingError(globwalk::GlobError),
@@ -85,13 +123,69 @@ impl fmt::Display for Error {
                 path.to_string_lossy()
             ),
             Error::ProcessError(ref _exit) => write!(f, "The process is not successful."),
+            Error::EmptyMasterNoteError(ref path) => write!(
+                f,
+                "The master note at path '{}' has no notes to include.",
+                path.to_string_lossy()
+            ),
+            Error::FrontMatterError(ref path, ref error) => write!(
+                f,
+                "The front matter of the note at path '{}' is invalid: {}",
+                path.to_string_lossy(),
+                error
+            ),
+            Error::UnknownMasterNoteError(ref path, ref name) => write!(
+                f,
+                "The subject at path '{}' has no master note named '{}'.",
+                path.to_string_lossy(),
+                name
+            ),
+            Error::MissingMasterNoteError(ref path) => write!(
+                f,
+                "The master note at path '{}' does not exist.",
+                path.to_string_lossy()
+            ),
+            Error::SlugCollisionError(ref path, ref existing, ref colliding) => write!(
+                f,
+                "'{}' has the same file name with '{}' at path '{}'.",
+                colliding,
+                existing,
+                path.to_string_lossy()
+            ),
+            Error::CompilationError(count) => write!(f, "{} note(s) failed to compile.", count),
+            Error::ThreadPoolError(ref message) => {
+                write!(
+                    f,
+                    "Cannot create the threads for the compilation: {}",
+                    message
+                )
+            }
             Error::IoError(ref err) => err.fmt(f),
             Error::MissingDataError(ref p) => write!(f, "{} is missing.", p),
+            Error::MissingTemplateVariableError(ref template, ref name) => write!(
+                f,
+                "The template '{}' requires the variable '{}' (e.g., `--var {}=VALUE`).",
+                template, name, name
+            ),
             Error::TomlValueError(ref p) => write!(f, "{} is invalid.", p),
             Error::TomlSerializeError(ref p) => write!(f, "{}", p),
             Error::HandlebarsTemplateError(ref p) => write!(f, "{}", p),
             Error::HandlebarsTemplateFileError(ref p) => write!(f, "{}", p),
             Error::HandlebarsRenderError(ref p) => write!(f, "{}", p),
+            Error::HandlebarsTemplateRenderError(ref p) => write!(f, "{}", p),
+            Error::JinjaTemplateError(ref p) => write!(f, "{}", p),
+            Error::JinjaRenderError(ref p) => write!(f, "{}", p),
+            Error::ScriptHelperError(ref path, ref error) => write!(
+                f,
+                "The helper script at path '{}' is invalid: {}",
+                path.to_string_lossy(),
+                error
+            ),
+            Error::HookError(ref hook, ref command, ref status) => write!(
+                f,
+                "The '{}' hook `{}` has failed ({}).",
+                hook, command, status
+            ),
             Error::GlobParsingError(ref error) => error.fmt(f),
             Error::Errors(ref errors) => {
                 for error in errors {
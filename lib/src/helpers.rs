@@ -1,2 +1,5 @@
 pub mod fs;
 pub mod handlebars;
+pub mod jinja;
+pub mod latex;
+pub mod shelf;
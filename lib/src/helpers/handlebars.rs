@@ -1,13 +1,18 @@
 //! This module simply defines all of the helpers to be used in Handlebars.
 //! The functions defined here are eventually registered in the profile templates.
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use chrono;
 use handlebars;
 use heck::{CamelCase, KebabCase, SnakeCase, TitleCase};
+use rhai;
 
 use crate::helpers;
+use crate::helpers::shelf::{ShelfQuery, ShelfQueryKind};
+use crate::slug::SlugStrategy;
 
 // TODO: Convert this into a macro.
 pub fn add_float(
@@ -194,6 +199,45 @@ handlebars::handlebars_helper!(lower_case: |s: str| s.to_lowercase());
 handlebars::handlebars_helper!(is_file: |s: str| PathBuf::from(s).is_file());
 handlebars::handlebars_helper!(is_dir: |s: str| PathBuf::from(s).is_dir());
 
+/// Creates the slug with the slug strategy of the shelf from the object (`shelf.slug`).
+pub fn slug(
+    h: &handlebars::Helper,
+    _: &handlebars::Handlebars,
+    ctx: &handlebars::Context,
+    _rc: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let s = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+    let strategy: SlugStrategy = ctx
+        .data()
+        .pointer("/shelf/slug")
+        .and_then(|v| v.as_str())
+        .and_then(|slug| slug.parse().ok())
+        .unwrap_or_default();
+
+    out.write(&strategy.slugify(s))?;
+    Ok(())
+}
+
+/// Renders the file name of the note from the object (`note.file`).
+/// This keeps the `{{note}}` of the older compilation commands working since the note is now a table.
+pub fn note(
+    _h: &handlebars::Helper,
+    _: &handlebars::Handlebars,
+    ctx: &handlebars::Context,
+    _rc: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let file = match ctx.data().get("note") {
+        Some(serde_json::Value::String(file)) => file.as_str(),
+        Some(note) => note.get("file").and_then(|v| v.as_str()).unwrap_or(""),
+        None => "",
+    };
+
+    out.write(file)?;
+    Ok(())
+}
+
 pub fn relpath(
     h: &handlebars::Helper,
     _: &handlebars::Handlebars,
@@ -209,6 +253,34 @@ pub fn relpath(
     Ok(())
 }
 
+// LaTeX escaping functions.
+// They write directly into the output so the escaped string is not escaped again by the profile escaping.
+pub fn latex_escape(
+    h: &handlebars::Helper,
+    _: &handlebars::Handlebars,
+    _: &handlebars::Context,
+    _rc: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let s = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+
+    out.write(&helpers::latex::escape(s))?;
+    Ok(())
+}
+
+pub fn latex_escape_url(
+    h: &handlebars::Helper,
+    _: &handlebars::Handlebars,
+    _: &handlebars::Context,
+    _rc: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let s = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+
+    out.write(&helpers::latex::escape_url(s))?;
+    Ok(())
+}
+
 pub fn reldate(
     h: &handlebars::Helper,
     _: &handlebars::Handlebars,
@@ -226,3 +298,158 @@ pub fn reldate(
     out.write(datetime_delta.format(format).to_string().as_ref())?;
     Ok(())
 }
+
+/// The helpers querying the shelf at render time (e.g., `{{#each (notes)}}`, `{{note-pdf "Calculus I" "Limits"}}`).
+/// The first parameter is the subject and the second is the title of the note.
+/// Both default to the subject and the note of the template object.
+#[derive(Debug, Clone, Copy)]
+pub struct ShelfQueryHelper(pub ShelfQueryKind);
+
+impl handlebars::HelperDef for ShelfQueryHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &handlebars::Helper<'reg, 'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        ctx: &'rc handlebars::Context,
+        _rc: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<Option<handlebars::ScopedJson<'reg, 'rc>>, handlebars::RenderError> {
+        let data = ctx.data();
+        let query = ShelfQuery::new(
+            helpers::shelf::object_shelf(
+                data.pointer("/shelf/path")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("."),
+                data.pointer("/shelf/slug").and_then(|v| v.as_str()),
+            ),
+            data.pointer("/subject/_full_name").and_then(|v| v.as_str()),
+            data.pointer("/note/title").and_then(|v| v.as_str()),
+        );
+        let subject = h.param(0).and_then(|v| v.value().as_str());
+        let note = h.param(1).and_then(|v| v.value().as_str());
+
+        let value = query
+            .run(self.0, subject, note)
+            .map_err(|e| handlebars::RenderError::new(e.to_string()))?;
+
+        Ok(Some(handlebars::ScopedJson::Derived(match value {
+            Some(value) => handlebars::to_json(value),
+            None => handlebars::JsonValue::Null,
+        })))
+    }
+}
+
+/// A user-defined helper from a Rhai script in the profile.
+///
+/// The script has the following variables in its scope:
+///
+/// * `params` - The array of the parameters given to the helper.
+/// * `hash` - The map of the hash parameters given to the helper (e.g., `{{format-week date offset=3}}`).
+/// * `context` - The current context of the template (e.g., the item inside of an `each` block).
+/// * `root` - The whole object of the template.
+///
+/// The value of the last expression of the script is the result of the helper.
+/// The same helper is also registered as a Jinja function (see `helpers::jinja::script_helper`).
+#[derive(Clone)]
+pub struct ScriptHelper {
+    name: String,
+    engine: Arc<rhai::Engine>,
+    script: rhai::AST,
+}
+
+/// The maximum number of operations of a script helper for each call so an endless loop fails the rendering instead of hanging.
+const SCRIPT_MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The maximum depth of the function calls of a script helper.
+const SCRIPT_MAX_CALL_LEVELS: usize = 32;
+
+/// The maximum length of the strings, arrays, and maps in a script helper.
+const SCRIPT_MAX_SIZE: usize = 1_000_000;
+
+impl ScriptHelper {
+    /// Creates the engine for the script helpers with the limits for the untrusted scripts.
+    /// Exceeding any of the limits fails the helper with a render error.
+    pub fn engine() -> rhai::Engine {
+        let mut engine = rhai::Engine::new();
+        engine
+            .set_max_operations(SCRIPT_MAX_OPERATIONS)
+            .set_max_call_levels(SCRIPT_MAX_CALL_LEVELS)
+            .set_max_expr_depths(SCRIPT_MAX_CALL_LEVELS, SCRIPT_MAX_CALL_LEVELS)
+            .set_max_string_size(SCRIPT_MAX_SIZE)
+            .set_max_array_size(SCRIPT_MAX_SIZE)
+            .set_max_map_size(SCRIPT_MAX_SIZE);
+
+        engine
+    }
+
+    /// Compiles the script into a helper.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        engine: Arc<rhai::Engine>,
+        script: &str,
+    ) -> Result<Self, rhai::ParseError> {
+        let script = engine.compile(script)?;
+
+        Ok(Self {
+            name: name.as_ref().to_string(),
+            engine,
+            script,
+        })
+    }
+
+    /// Runs the script with the given variables and returns the value of its last expression.
+    /// The error is the message for the render error of the template engine.
+    pub fn run<P, H, C, R>(
+        &self,
+        params: P,
+        hash: H,
+        context: C,
+        root: R,
+    ) -> Result<rhai::Dynamic, String>
+    where
+        P: serde::Serialize,
+        H: serde::Serialize,
+        C: serde::Serialize,
+        R: serde::Serialize,
+    {
+        let script_error =
+            |e: &dyn std::fmt::Display| format!("Helper '{}' failed: {}", self.name, e);
+
+        let mut scope = rhai::Scope::new();
+        for (name, value) in [
+            ("params", rhai::serde::to_dynamic(params)),
+            ("hash", rhai::serde::to_dynamic(hash)),
+            ("context", rhai::serde::to_dynamic(context)),
+            ("root", rhai::serde::to_dynamic(root)),
+        ] {
+            scope.push_dynamic(name, value.map_err(|e| script_error(&e))?);
+        }
+
+        self.engine
+            .eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &self.script)
+            .map_err(|e| script_error(&e))
+    }
+}
+
+impl handlebars::HelperDef for ScriptHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &handlebars::Helper<'reg, 'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        ctx: &'rc handlebars::Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<Option<handlebars::ScopedJson<'reg, 'rc>>, handlebars::RenderError> {
+        let params: Vec<&handlebars::JsonValue> = h.params().iter().map(|p| p.value()).collect();
+        let hash: BTreeMap<&str, &handlebars::JsonValue> =
+            h.hash().iter().map(|(k, v)| (*k, v.value())).collect();
+        let context = rc.evaluate(ctx, "this")?;
+
+        let result = self
+            .run(params, hash, context.as_json(), ctx.data())
+            .map_err(handlebars::RenderError::new)?;
+        let result: handlebars::JsonValue = rhai::serde::from_dynamic(&result).map_err(|e| {
+            handlebars::RenderError::new(format!("Helper '{}' failed: {}", self.name, e))
+        })?;
+
+        Ok(Some(handlebars::ScopedJson::Derived(result)))
+    }
+}
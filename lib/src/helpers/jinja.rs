@@ -0,0 +1,239 @@
+//! This module defines the helpers ported from the Handlebars helpers to be used in Jinja templates.
+//! The letter case conversions, the LaTeX escaping, and the slug are filters (e.g., `{{ note.title | kebab_case }}`) while the rest are functions.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use chrono;
+use heck::{CamelCase, KebabCase, SnakeCase, TitleCase};
+use minijinja::value::{Kwargs, Rest, Value};
+use minijinja::State;
+
+use crate::helpers;
+use crate::helpers::handlebars::ScriptHelper;
+use crate::helpers::shelf::{ShelfQuery, ShelfQueryKind};
+use crate::slug::SlugStrategy;
+
+// Mathematical functions.
+// Jinja already has the arithmetic operators so these are only here for parity with the Handlebars helpers.
+pub fn add_float(operands: Rest<f64>) -> f64 {
+    operands.iter().sum()
+}
+
+pub fn add_int(operands: Rest<i64>) -> i64 {
+    operands.iter().sum()
+}
+
+pub fn sub_float(operands: Rest<f64>) -> f64 {
+    match operands.split_first() {
+        Some((first, rest)) => rest.iter().fold(*first, |acc, value| acc - value),
+        None => 0.0,
+    }
+}
+
+pub fn sub_int(operands: Rest<i64>) -> i64 {
+    match operands.split_first() {
+        Some((first, rest)) => rest.iter().fold(*first, |acc, value| acc - value),
+        None => 0,
+    }
+}
+
+pub fn div_float(operands: Rest<f64>) -> f64 {
+    match operands.split_first() {
+        Some((first, rest)) => rest.iter().fold(*first, |acc, value| acc / value),
+        None => 0.0,
+    }
+}
+
+pub fn div_int(operands: Rest<i64>) -> Result<i64, minijinja::Error> {
+    match operands.split_first() {
+        Some((first, rest)) => rest.iter().try_fold(*first, |acc, value| {
+            acc.checked_div(*value).ok_or_else(|| {
+                minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, "division by zero")
+            })
+        }),
+        None => Ok(1),
+    }
+}
+
+pub fn mul_float(operands: Rest<f64>) -> f64 {
+    operands.iter().product()
+}
+
+pub fn mul_int(operands: Rest<i64>) -> i64 {
+    operands.iter().product()
+}
+
+// Letter case filters.
+pub fn kebab_case(s: &str) -> String {
+    s.to_kebab_case()
+}
+
+pub fn snake_case(s: &str) -> String {
+    s.to_snake_case()
+}
+
+pub fn title_case(s: &str) -> String {
+    s.to_title_case()
+}
+
+pub fn camel_case(s: &str) -> String {
+    s.to_camel_case()
+}
+
+pub fn upper_case(s: &str) -> String {
+    s.to_uppercase()
+}
+
+pub fn lower_case(s: &str) -> String {
+    s.to_lowercase()
+}
+
+// LaTeX escaping filters.
+// The results are marked as safe so they are not escaped again by the profile escaping.
+pub fn latex_escape(s: &str) -> Value {
+    Value::from_safe_string(helpers::latex::escape(s))
+}
+
+pub fn latex_escape_url(s: &str) -> Value {
+    Value::from_safe_string(helpers::latex::escape_url(s))
+}
+
+// Miscellaneous functions.
+/// Creates the slug with the slug strategy of the shelf from the object (`shelf.slug`).
+pub fn slug(
+    state: &State,
+    s: &str,
+) -> String {
+    let strategy: SlugStrategy = state
+        .lookup("shelf")
+        .and_then(|shelf| shelf.get_attr("slug").ok())
+        .and_then(|slug| slug.as_str().and_then(|slug| slug.parse().ok()))
+        .unwrap_or_default();
+
+    strategy.slugify(s)
+}
+
+pub fn is_file(s: &str) -> bool {
+    PathBuf::from(s).is_file()
+}
+
+pub fn is_dir(s: &str) -> bool {
+    PathBuf::from(s).is_dir()
+}
+
+pub fn relpath(
+    dst: &str,
+    base: &str,
+) -> String {
+    helpers::fs::relative_path_from(PathBuf::from(dst), PathBuf::from(base))
+        .unwrap_or(PathBuf::new())
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn reldate(
+    format: Option<&str>,
+    relative_days: Option<i64>,
+) -> String {
+    let now = chrono::Local::now();
+    let days = chrono::Duration::days(relative_days.unwrap_or(0));
+
+    (now + days).format(format.unwrap_or("%F")).to_string()
+}
+
+// Shelf query functions.
+// Similar to the Handlebars helpers, the subject and the note default to the ones from the template object.
+pub fn notes(
+    state: &State,
+    subject: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    query_shelf(state, ShelfQueryKind::Notes, subject, None)
+}
+
+pub fn note_count(
+    state: &State,
+    subject: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    query_shelf(state, ShelfQueryKind::NoteCount, subject, None)
+}
+
+pub fn prev_note(
+    state: &State,
+    subject: Option<&str>,
+    note: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    query_shelf(state, ShelfQueryKind::PreviousNote, subject, note)
+}
+
+pub fn next_note(
+    state: &State,
+    subject: Option<&str>,
+    note: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    query_shelf(state, ShelfQueryKind::NextNote, subject, note)
+}
+
+pub fn note_pdf(
+    state: &State,
+    subject: Option<&str>,
+    note: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    query_shelf(state, ShelfQueryKind::NotePdf, subject, note)
+}
+
+fn query_shelf(
+    state: &State,
+    kind: ShelfQueryKind,
+    subject: Option<&str>,
+    note: Option<&str>,
+) -> Result<Value, minijinja::Error> {
+    let lookup = |table: &str, key: &str| {
+        state
+            .lookup(table)
+            .and_then(|value| value.get_attr(key).ok())
+            .and_then(|value| value.as_str().map(String::from))
+    };
+    let query = ShelfQuery::new(
+        helpers::shelf::object_shelf(
+            lookup("shelf", "path").unwrap_or(".".to_string()),
+            lookup("shelf", "slug").as_deref(),
+        ),
+        lookup("subject", "_full_name").as_deref(),
+        lookup("note", "title").as_deref(),
+    );
+
+    query
+        .run(kind, subject, note)
+        .map(|value| value.map(Value::from_serialize).unwrap_or(Value::from(())))
+        .map_err(|e| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, e.to_string()))
+}
+
+/// Calls the script helper with the arguments of the Jinja function.
+/// The keyword arguments are the `hash` of the script.
+/// Since Jinja has no context like Handlebars, both `context` and `root` are the variables of the template.
+pub fn script_helper(
+    helper: &ScriptHelper,
+    state: &State,
+    params: Rest<Value>,
+    hash: Kwargs,
+) -> Result<Value, minijinja::Error> {
+    let mut hash_values: BTreeMap<String, Value> = BTreeMap::new();
+    for key in hash.args() {
+        hash_values.insert(key.to_string(), hash.get(key)?);
+    }
+
+    // The functions and other globals of the environment are not part of the object.
+    let globals: Vec<&str> = state.env().globals().map(|(name, _)| name).collect();
+    let variables: BTreeMap<String, Value> = state
+        .known_variables()
+        .iter()
+        .filter(|name| !globals.contains(&name.as_ref()))
+        .filter_map(|name| state.lookup(name).map(|value| (name.to_string(), value)))
+        .collect();
+
+    helper
+        .run(params.0, hash_values, &variables, &variables)
+        .map(|result| Value::from_serialize(&result))
+        .map_err(|e| minijinja::Error::new(minijinja::ErrorKind::InvalidOperation, e))
+}
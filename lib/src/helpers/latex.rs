@@ -0,0 +1,58 @@
+//! Escaping the strings to be used in LaTeX documents.
+
+/// Escapes the special characters of LaTeX (i.e., `& % $ # _ { } ~ ^ \`) to be typeset as they are.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Escapes the URL to be used in the verbatim-like commands such as `\url` and `\href`.
+/// Only the characters that still break the arguments of the commands (i.e., `% # { }`) are escaped.
+pub fn escape_url(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if matches!(c, '%' | '#' | '{' | '}') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latex_escape() {
+        assert_eq!(
+            escape("Profit & Loss: 50% Margins"),
+            "Profit \\& Loss: 50\\% Margins"
+        );
+        assert_eq!(
+            escape("$x_1^2$ {#} ~ \\"),
+            "\\$x\\_1\\textasciicircum{}2\\$ \\{\\#\\} \\textasciitilde{} \\textbackslash{}"
+        );
+        assert_eq!(
+            escape_url("https://example.com/~me/a_b?q=50%#top"),
+            "https://example.com/~me/a_b?q=50\\%\\#top"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! The shelf queries for the template helpers (e.g., listing the notes of a subject).
+//!
+//! The queries are made at render time with the shelf, the subject, and the note from the object of the template.
+//! The subject and the note of the object are used when they are not given to the helper.
+
+use std::path::{Path, PathBuf};
+
+use toml;
+
+use crate::compile::CompilationEnvironment;
+use crate::helpers;
+use crate::masternote::MasterNoteGenerator;
+use crate::note::Note;
+use crate::shelf::{Shelf, ShelfData, ShelfItem};
+use crate::subjects::Subject;
+use crate::Result;
+
+/// The queries available as template helpers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShelfQueryKind {
+    /// The notes of the subject in the order of its master note.
+    Notes,
+
+    /// The number of the notes of the subject.
+    NoteCount,
+
+    /// The note before the note in the order of the master note.
+    PreviousNote,
+
+    /// The note after the note in the order of the master note.
+    NextNote,
+
+    /// The path of the resulting PDF of the note relative to the subject of the object.
+    NotePdf,
+}
+
+/// The context of the shelf queries taken from the object of the template.
+#[derive(Debug, Clone)]
+pub struct ShelfQuery {
+    shelf: Shelf,
+    subject: Option<String>,
+    note: Option<String>,
+}
+
+/// Creates the shelf from the path (`shelf.path`) and the slug strategy (`shelf.slug`) of the object.
+/// An unknown slug strategy is replaced with the default one.
+pub fn object_shelf<P: AsRef<Path>>(
+    path: P,
+    slug: Option<&str>,
+) -> Shelf {
+    let mut shelf = Shelf::new(path);
+    if let Some(slug) = slug.and_then(|slug| slug.parse().ok()) {
+        shelf.set_slug(slug);
+    }
+
+    shelf
+}
+
+impl ShelfQuery {
+    /// Creates the query context with the shelf, the full name of the subject (`subject._full_name`), and the title of the note (`note.title`) from the object.
+    pub fn new(
+        shelf: Shelf,
+        subject: Option<&str>,
+        note: Option<&str>,
+    ) -> Self {
+        Self {
+            shelf,
+            subject: subject.map(String::from),
+            note: note.map(String::from),
+        }
+    }
+
+    /// Runs the query with the given subject and note title.
+    /// The subject and the note of the object are used in their place if they are not given.
+    ///
+    /// It will return `None` if there is no resulting value (e.g., the first note has no previous note).
+    pub fn run(
+        &self,
+        kind: ShelfQueryKind,
+        subject: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<Option<toml::Value>> {
+        let subject = match subject.or(self.subject.as_deref()) {
+            Some(subject) => Subject::from_shelf(subject, &self.shelf)?,
+            None => return Ok(None),
+        };
+        let note = note.or(self.note.as_deref()).map(|title| {
+            let mut note = Note::new(title);
+            note.set_slug(self.shelf.slug());
+            note
+        });
+        let notes = MasterNoteGenerator::new(&self.shelf).notes(&subject, note.as_ref())?;
+        let position = note.as_ref().and_then(|note| {
+            notes
+                .iter()
+                .position(|other| other.title() == note.title() || other.stem() == note.stem())
+        });
+
+        let value = match kind {
+            ShelfQueryKind::Notes => {
+                // The note of the object is only used for ordering.
+                let notes: Vec<toml::Value> = notes
+                    .iter()
+                    .filter(|other| other.is_item_valid((&subject, &self.shelf)))
+                    .map(|other| ShelfData::data(other, (&subject, &self.shelf)))
+                    .collect();
+
+                Some(toml::Value::from(notes))
+            }
+            ShelfQueryKind::NoteCount => {
+                let count = notes
+                    .iter()
+                    .filter(|other| other.is_item_valid((&subject, &self.shelf)))
+                    .count();
+
+                Some(toml::Value::from(count as i64))
+            }
+            ShelfQueryKind::PreviousNote => position
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| notes.get(index))
+                .map(|other| ShelfData::data(other, (&subject, &self.shelf))),
+            ShelfQueryKind::NextNote => position
+                .and_then(|index| notes.get(index + 1))
+                .map(|other| ShelfData::data(other, (&subject, &self.shelf))),
+            ShelfQueryKind::NotePdf => match position {
+                Some(index) => {
+                    let pdf_path = self.pdf_path(&subject, &notes[index]);
+
+                    Some(toml::Value::from(pdf_path.to_string_lossy().to_string()))
+                }
+                None => None,
+            },
+        };
+
+        Ok(value)
+    }
+
+    /// Returns the path of the resulting PDF of the note relative to the subject of the object (or the shelf if there's none).
+    fn pdf_path(
+        &self,
+        subject: &Subject,
+        note: &Note,
+    ) -> PathBuf {
+        let shelf_config = self.shelf.get_config().unwrap_or_default();
+        let subject_config = subject.get_config(&self.shelf).unwrap_or_default();
+
+        let mut env = CompilationEnvironment::new(subject, &self.shelf);
+        env.output_dirs_from_config(&shelf_config, &subject_config);
+        let pdf_path = env.output_pdf_path(note);
+
+        let base = match &self.subject {
+            Some(subject) => {
+                let mut subject = Subject::new(subject);
+                subject.set_slug(self.shelf.slug());
+                subject.path_in_shelf(&self.shelf)
+            }
+            None => self.shelf.path(),
+        };
+
+        helpers::fs::relative_path_from(pdf_path, base).unwrap_or(PathBuf::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::fs;
+    use tempfile;
+
+    #[test]
+    fn shelf_queries() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let mut shelf = Shelf::new(tmp_dir.path());
+        shelf.export()?;
+        fs::write(shelf.metadata_path(), "pdf_dir = 'pdf'").map_err(Error::IoError)?;
+
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+        fs::write(
+            subject.metadata_path_in_shelf(&shelf),
+            "name = 'Calculus'\norder = ['Limits', 'Derivatives', 'Integrals']",
+        )
+        .map_err(Error::IoError)?;
+        for title in &["Derivatives", "Limits"] {
+            let note = Note::new(title);
+            fs::write(note.path_in_shelf((&subject, &shelf)), note.front_matter())
+                .map_err(Error::IoError)?;
+        }
+
+        let query = ShelfQuery::new(shelf.clone(), Some("Calculus"), Some("Integrals"));
+        let title = |value: Option<toml::Value>| {
+            value.and_then(|value| {
+                value
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            })
+        };
+
+        let notes = query.run(ShelfQueryKind::Notes, None, None)?.unwrap();
+        let titles: Vec<&str> = notes
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|note| note.get("title").and_then(|v| v.as_str()))
+            .collect();
+        assert_eq!(titles, vec!["Limits", "Derivatives"]);
+        assert_eq!(
+            query.run(ShelfQueryKind::NoteCount, None, None)?,
+            Some(toml::Value::from(2))
+        );
+
+        // The note of the object is placed in the order even if it is not in the shelf yet.
+        assert_eq!(
+            title(query.run(ShelfQueryKind::PreviousNote, None, None)?),
+            Some("Derivatives".to_string())
+        );
+        assert_eq!(query.run(ShelfQueryKind::NextNote, None, None)?, None);
+        assert_eq!(
+            title(query.run(ShelfQueryKind::NextNote, None, Some("Limits"))?),
+            Some("Derivatives".to_string())
+        );
+        assert_eq!(
+            query.run(
+                ShelfQueryKind::PreviousNote,
+                Some("Calculus"),
+                Some("Limits")
+            )?,
+            None
+        );
+        assert_eq!(
+            query.run(ShelfQueryKind::NotePdf, None, Some("Limits"))?,
+            Some(toml::Value::from("../pdf/calculus-limits.pdf"))
+        );
+
+        assert!(query
+            .run(ShelfQueryKind::Notes, Some("Physics"), None)
+            .is_err());
+
+        Ok(())
+    }
+}
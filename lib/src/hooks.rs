@@ -0,0 +1,547 @@
+//! Lifecycle hooks run before and after the operations on the shelf (e.g., committing the notes after adding them).
+//!
+//! A hook can be an executable file named after the event in the hooks folder of the profile (`hooks/`) or the shelf (`.lanoma/hooks/`).
+//! It can also be a command in the `hooks` table of the profile and shelf metadata.
+//! The hooks of the shelf are only run if the shelf is trusted by the profile (`trusted_shelves`) since running them is running the commands from the shelf.
+//! Each hook receives the affected items as JSON on its standard input and through the environment variables.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::result;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::compile::{Compilable, CompilationEnvironment, CompileResult};
+use crate::error::Error;
+use crate::index::INDEX_FOLDER;
+use crate::masternote::MasterNote;
+use crate::note::Note;
+use crate::profile::Profile;
+use crate::shelf::{Shelf, ShelfItem};
+use crate::subjects::Subject;
+use crate::Result;
+
+/// The name of the folder of the executable hooks in the profile and in the `.lanoma` folder of the shelf.
+pub const HOOKS_FOLDER: &str = "hooks";
+
+/// The operations with hooks.
+///
+/// It is (de)serialized with its name as it is mainly used as a key of a table.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub enum HookEvent {
+    /// Before adding subjects or notes.
+    PreAdd,
+
+    /// After adding subjects or notes.
+    PostAdd,
+
+    /// Before removing subjects or notes.
+    PreRemove,
+
+    /// Before compiling notes or master notes.
+    PreCompile,
+
+    /// After compiling notes or master notes.
+    PostCompile,
+
+    /// After generating master notes.
+    PostMaster,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let name = match self {
+            HookEvent::PreAdd => "pre-add",
+            HookEvent::PostAdd => "post-add",
+            HookEvent::PreRemove => "pre-remove",
+            HookEvent::PreCompile => "pre-compile",
+            HookEvent::PostCompile => "post-compile",
+            HookEvent::PostMaster => "post-master",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for HookEvent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pre-add" => Ok(HookEvent::PreAdd),
+            "post-add" => Ok(HookEvent::PostAdd),
+            "pre-remove" => Ok(HookEvent::PreRemove),
+            "pre-compile" => Ok(HookEvent::PreCompile),
+            "post-compile" => Ok(HookEvent::PostCompile),
+            "post-master" => Ok(HookEvent::PostMaster),
+            _ => Err(Error::ValueError),
+        }
+    }
+}
+
+impl TryFrom<String> for HookEvent {
+    type Error = String;
+
+    fn try_from(s: String) -> result::Result<Self, Self::Error> {
+        s.parse().map_err(|_e| format!("unknown hook '{}'", s))
+    }
+}
+
+impl From<HookEvent> for String {
+    fn from(event: HookEvent) -> Self {
+        event.to_string()
+    }
+}
+
+/// The hook commands of an event.
+/// It can be a single command or a list of commands run in order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum HookCommand {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl HookCommand {
+    /// Returns the hook as a list of commands.
+    pub fn commands(&self) -> Vec<String> {
+        match self {
+            HookCommand::Single(command) => vec![command.clone()],
+            HookCommand::Multiple(commands) => commands.clone(),
+        }
+    }
+}
+
+/// The kind of the item affected by an operation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookItemKind {
+    Subject,
+    Note,
+    Master,
+}
+
+/// An item affected by an operation.
+#[derive(Serialize, Clone, Debug)]
+pub struct HookItem {
+    pub kind: HookItemKind,
+
+    /// The title of the note or the name of the subject.
+    pub name: String,
+
+    /// The full name of the subject of the item.
+    pub subject: String,
+
+    /// The path of the item in the shelf.
+    pub path: PathBuf,
+
+    /// The path of the resulting PDF of the compilable items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf: Option<PathBuf>,
+
+    /// Whether the item has been successfully compiled (only for the `post-compile` hook).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiled: Option<bool>,
+}
+
+impl HookItem {
+    /// Creates the item of the subject.
+    pub fn subject(
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> Self {
+        Self {
+            kind: HookItemKind::Subject,
+            name: subject.name(),
+            subject: subject.full_name().clone(),
+            path: subject.path_in_shelf(shelf),
+            pdf: None,
+            compiled: None,
+        }
+    }
+
+    /// Creates the item of the note.
+    pub fn note(
+        note: &Note,
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> Self {
+        Self {
+            kind: HookItemKind::Note,
+            name: note.title(),
+            subject: subject.full_name().clone(),
+            path: note.path_in_shelf((subject, shelf)),
+            pdf: None,
+            compiled: None,
+        }
+    }
+
+    /// Creates the item of the master note.
+    pub fn master_note(
+        master_note: &MasterNote,
+        shelf: &Shelf,
+    ) -> Self {
+        Self {
+            kind: HookItemKind::Master,
+            name: master_note.subject().name(),
+            subject: master_note.subject().full_name().clone(),
+            path: master_note.path_in_shelf(shelf),
+            pdf: None,
+            compiled: None,
+        }
+    }
+
+    /// Creates the item of the note or the master note with the path from its data.
+    pub fn compilable(
+        kind: HookItemKind,
+        compilable: &dyn Compilable,
+        env: &CompilationEnvironment,
+    ) -> Self {
+        let path = compilable
+            .data(&env.subject, &env.shelf)
+            .get("path_in_shelf")
+            .and_then(|path| path.as_str())
+            .map(PathBuf::from)
+            .unwrap_or_default();
+
+        Self {
+            kind,
+            name: compilable.name(),
+            subject: env.subject.full_name().clone(),
+            path,
+            pdf: Some(env.output_pdf_path(compilable)),
+            compiled: None,
+        }
+    }
+
+    /// Creates the items of all of the compilables from the environments.
+    /// The items are grouped in the same order as the environments.
+    pub fn from_environments(
+        kind: HookItemKind,
+        envs: &[CompilationEnvironment],
+    ) -> Vec<Vec<Self>> {
+        envs.iter()
+            .map(|env| {
+                env.compilables
+                    .iter()
+                    .map(|compilable| Self::compilable(kind, compilable.as_ref(), env))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Marks the items whether they are compiled from the results in the same order as the items.
+    /// The items skipped from an earlier failure are removed.
+    pub fn mark_compiled(
+        items: Vec<Vec<Self>>,
+        results: &[CompileResult],
+    ) -> Vec<Self> {
+        items
+            .into_iter()
+            .zip(results.iter())
+            .flat_map(|(items, result)| {
+                items.into_iter().filter_map(move |mut item| {
+                    let has_name = |compilable: &dyn Compilable| compilable.name() == item.name;
+
+                    if result.compiled.iter().any(|c| has_name(c.as_ref())) {
+                        item.compiled = Some(true);
+                    } else if result
+                        .failed
+                        .iter()
+                        .any(|f| has_name(f.compilable.as_ref()))
+                    {
+                        item.compiled = Some(false);
+                    } else {
+                        return None;
+                    }
+
+                    Some(item)
+                })
+            })
+            .collect()
+    }
+}
+
+/// The data sent to the hooks as JSON.
+#[derive(Serialize)]
+struct HookPayload<'a> {
+    hook: HookEvent,
+    shelf: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<&'a Path>,
+    items: &'a [HookItem],
+}
+
+/// The hooks of a profile or a shelf.
+#[derive(Debug, Clone)]
+struct HookSource {
+    folder: PathBuf,
+    commands: BTreeMap<HookEvent, HookCommand>,
+}
+
+/// The hooks from the profile and the trusted shelf.
+/// The hooks of the profile are run before the hooks of the shelf.
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    shelf: PathBuf,
+    profile: Option<PathBuf>,
+    sources: Vec<HookSource>,
+}
+
+impl Hooks {
+    /// Collects the hooks of the profile, if there's any, and the hooks of the shelf if the profile trusts the shelf.
+    /// Without a profile, the hooks of the shelf are never run.
+    pub fn new(
+        shelf: &Shelf,
+        profile: Option<&Profile>,
+    ) -> Result<Self> {
+        let mut sources = vec![];
+        if let Some(profile) = profile {
+            sources.push(HookSource {
+                folder: profile.path().join(HOOKS_FOLDER),
+                commands: profile.config().hooks.clone(),
+            });
+
+            if profile.config().trusts_shelf(shelf.path()) {
+                sources.push(HookSource {
+                    folder: shelf.path().join(INDEX_FOLDER).join(HOOKS_FOLDER),
+                    commands: match shelf.metadata_path().is_file() {
+                        true => shelf.get_config()?.hooks,
+                        false => BTreeMap::new(),
+                    },
+                });
+            }
+        }
+
+        Ok(Self {
+            shelf: shelf.path(),
+            profile: profile.map(|profile| profile.path()),
+            sources,
+        })
+    }
+
+    /// Runs the hooks of the event in the shelf folder with the items.
+    /// For each source, the executable hook is run first then the commands.
+    ///
+    /// It stops at the first failed hook and returns an error.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        items: &[HookItem],
+    ) -> Result<()> {
+        let payload = serde_json::to_string(&HookPayload {
+            hook: event,
+            shelf: &self.shelf,
+            profile: self.profile.as_deref(),
+            items,
+        })
+        .map_err(|_e| Error::ValueError)?;
+
+        for source in self.sources.iter() {
+            let executable = source.folder.join(event.to_string());
+            if executable.is_file() {
+                let command = process::Command::new(&executable);
+                self.run_hook(
+                    event,
+                    command,
+                    &executable.to_string_lossy(),
+                    &payload,
+                    items,
+                )?;
+            }
+
+            if let Some(hook) = source.commands.get(&event) {
+                for command in hook.commands() {
+                    self.run_hook(event, shell_command(&command), &command, &payload, items)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a hook with the payload in its standard input and the items in the environment variables.
+    fn run_hook(
+        &self,
+        event: HookEvent,
+        mut command: process::Command,
+        name: &str,
+        payload: &str,
+        items: &[HookItem],
+    ) -> Result<()> {
+        let join_paths = |paths: Vec<&PathBuf>| {
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let paths = join_paths(items.iter().map(|item| &item.path).collect());
+        // Only the PDFs that are not known to be failed are included.
+        let pdfs = join_paths(
+            items
+                .iter()
+                .filter(|item| item.compiled != Some(false))
+                .filter_map(|item| item.pdf.as_ref())
+                .collect(),
+        );
+
+        command
+            .current_dir(&self.shelf)
+            .env("LANOMA_HOOK", event.to_string())
+            .env("LANOMA_SHELF", &self.shelf)
+            .env("LANOMA_ITEMS", paths)
+            .env("LANOMA_PDFS", pdfs)
+            .stdin(Stdio::piped());
+        if let Some(profile) = &self.profile {
+            command.env("LANOMA_PROFILE", profile);
+        }
+
+        let mut child = command.spawn().map_err(Error::IoError)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // The hook may exit without reading the payload.
+            match stdin.write_all(payload.as_bytes()) {
+                Err(error) if error.kind() != io::ErrorKind::BrokenPipe => {
+                    return Err(Error::IoError(error))
+                }
+                _ => (),
+            }
+        }
+
+        let status = child.wait().map_err(Error::IoError)?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(Error::HookError(
+                event.to_string(),
+                name.to_string(),
+                status,
+            )),
+        }
+    }
+}
+
+/// Creates the command to be run with the shell of the platform.
+fn shell_command(command: &str) -> process::Command {
+    let mut shell = match cfg!(windows) {
+        true => {
+            let mut shell = process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        }
+        false => {
+            let mut shell = process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        }
+    };
+    shell.arg(command);
+
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileBuilder;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile;
+
+    #[test]
+    fn shelf_hooks() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let mut shelf = Shelf::new(tmp_dir.path());
+        shelf.export()?;
+        fs::write(
+            shelf.metadata_path(),
+            r#"
+[hooks]
+pre-add = "cat > payload.json"
+post-add = ["printf '%s' \"$LANOMA_HOOK\" > hook.txt", "printf '%s' \"$LANOMA_ITEMS\" > items.txt"]
+pre-remove = "exit 3"
+"#,
+        )
+        .map_err(Error::IoError)?;
+
+        let hooks_folder = shelf.path().join(INDEX_FOLDER).join(HOOKS_FOLDER);
+        fs::create_dir_all(&hooks_folder).map_err(Error::IoError)?;
+        let executable = hooks_folder.join("post-add");
+        fs::write(&executable, "#!/bin/sh\necho executable > executable.txt\n")
+            .map_err(Error::IoError)?;
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))
+            .map_err(Error::IoError)?;
+
+        let items = vec![
+            HookItem::subject(&Subject::new("Calculus"), &shelf),
+            HookItem::subject(&Subject::new("Physics"), &shelf),
+        ];
+
+        // The hooks of the shelf are ignored unless the profile trusts the shelf.
+        let profile_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let mut profile_builder = ProfileBuilder::new();
+        profile_builder.path(profile_dir.path());
+        profile_builder.build().export()?;
+        let profile = Profile::from(profile_dir.path())?;
+        for hooks in &[
+            Hooks::new(&shelf, None)?,
+            Hooks::new(&shelf, Some(&profile))?,
+        ] {
+            hooks.run(HookEvent::PreRemove, &items)?;
+        }
+
+        let mut config = profile.config().clone();
+        config.trusted_shelves.push(shelf.path());
+        fs::write(profile.metadata_path(), toml::to_string(&config).unwrap())
+            .map_err(Error::IoError)?;
+        let profile = Profile::from(profile_dir.path())?;
+        let hooks = Hooks::new(&shelf, Some(&profile))?;
+
+        hooks.run(HookEvent::PreAdd, &items)?;
+        let payload: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(shelf.path().join("payload.json")).map_err(Error::IoError)?,
+        )
+        .unwrap();
+        assert_eq!(payload["hook"], "pre-add");
+        assert_eq!(payload["items"][0]["kind"], "subject");
+        assert_eq!(payload["items"][1]["name"], "Physics");
+
+        hooks.run(HookEvent::PostAdd, &items)?;
+        assert!(shelf.path().join("executable.txt").is_file());
+        assert_eq!(
+            fs::read_to_string(shelf.path().join("hook.txt")).map_err(Error::IoError)?,
+            "post-add"
+        );
+        assert_eq!(
+            fs::read_to_string(shelf.path().join("items.txt")).map_err(Error::IoError)?,
+            format!(
+                "{}\n{}",
+                shelf.path().join("calculus").to_string_lossy(),
+                shelf.path().join("physics").to_string_lossy()
+            )
+        );
+
+        // The events without hooks do nothing.
+        hooks.run(HookEvent::PostMaster, &items)?;
+
+        match hooks.run(HookEvent::PreRemove, &items) {
+            Err(Error::HookError(hook, command, status)) => {
+                assert_eq!(hook, "pre-remove");
+                assert_eq!(command, "exit 3");
+                assert_eq!(status.code(), Some(3));
+            }
+            _ => panic!("The failed hook should return an error."),
+        }
+
+        Ok(())
+    }
+}
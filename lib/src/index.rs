@@ -0,0 +1,485 @@
+//! The persistent index of the shelf.
+//!
+//! The index caches the subjects and notes found in the shelf along with their metadata and compilation state.
+//! Each entry is validated with the modification time and the size of its file so the unchanged files are not read again.
+//! The files of the notes of each subject are also listed so an unchanged subject folder is not searched again.
+//! It is stored in the shelf as a TOML file at `.lanoma/index` and it can be rebuilt anytime from the filesystem.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use toml;
+
+use crate::config::SubjectConfig;
+use crate::error::Error;
+use crate::note::Note;
+use crate::slug::SlugStrategy;
+use crate::Result;
+
+/// The folder (relative to the shelf) containing the files managed by Lanoma.
+pub const INDEX_FOLDER: &str = ".lanoma";
+const INDEX_FILE: &str = "index";
+const INDEX_VERSION: u32 = 1;
+
+/// The modification time and the size of a file used for validating the entries.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FileStamp {
+    pub modified: DateTime<Utc>,
+    pub len: u64,
+}
+
+impl FileStamp {
+    /// Creates the stamp from the file at the given path.
+    /// It will return `None` if the file does not exist or the filesystem has no modification time.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+
+        Some(Self {
+            modified: DateTime::from(metadata.modified().ok()?),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// An indexed subject with its configuration from the metadata file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SubjectEntry {
+    pub stamp: FileStamp,
+    pub config: toml::Value,
+}
+
+/// An indexed note with the metadata from its front matter.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NoteEntry {
+    pub stamp: FileStamp,
+    pub note: Note,
+}
+
+/// The files matching the file globs of a subject.
+///
+/// The listing is validated with the stamp of the subject folder which changes when its files are added, removed, or renamed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NoteListing {
+    pub globs: Vec<String>,
+
+    /// The file names of the matching files including the ones that are not valid notes.
+    pub files: Vec<String>,
+
+    /// Placed last since the TOML tables have to come after the values.
+    pub stamp: FileStamp,
+}
+
+/// The state of the last compilation of a file.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CompileState {
+    pub succeeded: bool,
+    pub time: DateTime<Utc>,
+}
+
+/// The index of a shelf.
+///
+/// The entries are keyed by their path relative to the shelf.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShelfIndex {
+    version: u32,
+
+    /// The slug strategy used for the file names of the entries.
+    #[serde(default)]
+    slug: SlugStrategy,
+
+    #[serde(default)]
+    subjects: BTreeMap<String, SubjectEntry>,
+
+    #[serde(default)]
+    notes: BTreeMap<String, NoteEntry>,
+
+    #[serde(default)]
+    listings: BTreeMap<String, NoteListing>,
+
+    #[serde(default)]
+    compiled: BTreeMap<String, CompileState>,
+
+    /// Indicates the index has been changed since it was opened.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl Default for ShelfIndex {
+    fn default() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            slug: SlugStrategy::default(),
+            subjects: BTreeMap::new(),
+            notes: BTreeMap::new(),
+            listings: BTreeMap::new(),
+            compiled: BTreeMap::new(),
+            changed: false,
+        }
+    }
+}
+
+impl ShelfIndex {
+    /// Creates an empty index for the file names made with the slug strategy.
+    pub fn new(slug: SlugStrategy) -> Self {
+        Self {
+            slug,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the path of the index in the given shelf path.
+    pub fn path<P: AsRef<Path>>(shelf_path: P) -> PathBuf {
+        shelf_path.as_ref().join(INDEX_FOLDER).join(INDEX_FILE)
+    }
+
+    /// Opens the index of the shelf.
+    /// If the shelf has no index yet, it will return an empty index.
+    ///
+    /// It will return an error if the index is invalid or made from a different version or slug strategy.
+    pub fn open<P: AsRef<Path>>(
+        shelf_path: P,
+        slug: SlugStrategy,
+    ) -> Result<Self> {
+        let path = Self::path(shelf_path);
+        if !path.is_file() {
+            return Ok(Self::new(slug));
+        }
+
+        let content = fs::read_to_string(path).map_err(Error::IoError)?;
+        let index: Self = toml::from_str(&content).map_err(Error::TomlValueError)?;
+        match index.version == INDEX_VERSION && index.slug == slug {
+            true => Ok(index),
+            false => Err(Error::ValueError),
+        }
+    }
+
+    /// Writes the index into the shelf if it has been changed.
+    pub fn save<P: AsRef<Path>>(
+        &mut self,
+        shelf_path: P,
+    ) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let path = Self::path(shelf_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::IoError)?;
+        }
+
+        let content = toml::to_string(&self).map_err(Error::TomlSerializeError)?;
+        fs::write(path, content).map_err(Error::IoError)?;
+        self.changed = false;
+
+        Ok(())
+    }
+
+    /// Checks if the index has been changed since it was opened.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Marks the index as changed to be written when saved.
+    pub fn set_changed(&mut self) {
+        self.changed = true;
+    }
+
+    /// Returns the indexed subjects with their configuration.
+    pub fn subjects(&self) -> &BTreeMap<String, SubjectEntry> {
+        &self.subjects
+    }
+
+    /// Returns the indexed notes.
+    pub fn notes(&self) -> &BTreeMap<String, NoteEntry> {
+        &self.notes
+    }
+
+    /// Returns the configuration of the subject if the entry is still valid with the given stamp.
+    pub fn subject_config<P: AsRef<Path>>(
+        &self,
+        subject_path: P,
+        stamp: FileStamp,
+    ) -> Option<SubjectConfig> {
+        let entry = self.subjects.get(&key(subject_path))?;
+        match entry.stamp == stamp {
+            true => entry.config.clone().try_into().ok(),
+            false => None,
+        }
+    }
+
+    /// Adds (or replaces) the subject entry.
+    pub fn insert_subject<P: AsRef<Path>>(
+        &mut self,
+        subject_path: P,
+        stamp: FileStamp,
+        config: &SubjectConfig,
+    ) {
+        if let Ok(config) = toml::Value::try_from(config) {
+            self.subjects
+                .insert(key(subject_path), SubjectEntry { stamp, config });
+            self.changed = true;
+        }
+    }
+
+    /// Removes the subject along with the entries under it.
+    pub fn remove_subject<P: AsRef<Path>>(
+        &mut self,
+        subject_path: P,
+    ) {
+        let subject_path = subject_path.as_ref();
+        let is_outside = |entry: &String| !Path::new(entry).starts_with(subject_path);
+        let count = self.len();
+
+        self.subjects.retain(|entry, _| is_outside(entry));
+        self.notes.retain(|entry, _| is_outside(entry));
+        self.listings.retain(|entry, _| is_outside(entry));
+        self.compiled.retain(|entry, _| is_outside(entry));
+        if count != self.len() {
+            self.changed = true;
+        }
+    }
+
+    /// Returns the total number of the entries.
+    fn len(&self) -> usize {
+        self.subjects.len() + self.notes.len() + self.listings.len() + self.compiled.len()
+    }
+
+    /// Returns the file names of the notes of the subject if the listing is still valid with the given folder stamp and file globs.
+    pub fn listing<P: AsRef<Path>>(
+        &self,
+        subject_path: P,
+        stamp: FileStamp,
+        globs: &[String],
+    ) -> Option<&Vec<String>> {
+        let listing = self.listings.get(&key(subject_path))?;
+        match listing.stamp == stamp && listing.globs == globs {
+            true => Some(&listing.files),
+            false => None,
+        }
+    }
+
+    /// Adds (or replaces) the listing of the files of the subject.
+    pub fn insert_listing<P: AsRef<Path>>(
+        &mut self,
+        subject_path: P,
+        stamp: FileStamp,
+        globs: &[String],
+        files: Vec<String>,
+    ) {
+        self.listings.insert(
+            key(subject_path),
+            NoteListing {
+                globs: globs.to_vec(),
+                files,
+                stamp,
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Returns the note if the entry is still valid with the given stamp.
+    pub fn note<P: AsRef<Path>>(
+        &self,
+        note_path: P,
+        stamp: FileStamp,
+    ) -> Option<&Note> {
+        let entry = self.notes.get(&key(note_path))?;
+        match entry.stamp == stamp {
+            true => Some(&entry.note),
+            false => None,
+        }
+    }
+
+    /// Adds (or replaces) the note entry.
+    pub fn insert_note<P: AsRef<Path>>(
+        &mut self,
+        note_path: P,
+        stamp: FileStamp,
+        note: &Note,
+    ) {
+        self.notes.insert(
+            key(note_path),
+            NoteEntry {
+                stamp,
+                note: note.clone(),
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Removes the note entry along with its compilation state.
+    pub fn remove_note<P: AsRef<Path>>(
+        &mut self,
+        note_path: P,
+    ) {
+        let note_path = key(note_path);
+        let has_note = self.notes.remove(&note_path).is_some();
+        let has_state = self.compiled.remove(&note_path).is_some();
+
+        if has_note || has_state {
+            self.changed = true;
+        }
+    }
+
+    /// Removes the note entries directly under the subject that are not in the shelf anymore.
+    pub fn prune_notes<P: AsRef<Path>, S: AsRef<Path>>(
+        &mut self,
+        subject_path: P,
+        shelf_path: S,
+    ) {
+        let subject_path = subject_path.as_ref();
+        let shelf_path = shelf_path.as_ref();
+        let stale_notes: Vec<String> = self
+            .notes
+            .keys()
+            .filter(|entry| Path::new(entry).parent() == Some(subject_path))
+            .filter(|entry| !shelf_path.join(entry).is_file())
+            .cloned()
+            .collect();
+
+        for note_path in stale_notes {
+            self.remove_note(note_path);
+        }
+    }
+
+    /// Returns the state of the last compilation of the file.
+    pub fn compile_state<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Option<&CompileState> {
+        self.compiled.get(&key(path))
+    }
+
+    /// Records the state of the compilation of the file.
+    pub fn set_compile_state<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        succeeded: bool,
+    ) {
+        self.compiled.insert(
+            key(path),
+            CompileState {
+                succeeded,
+                time: DateTime::from(SystemTime::now()),
+            },
+        );
+        self.changed = true;
+    }
+}
+
+/// Returns the key of the entry from its path relative to the shelf.
+fn key<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref().to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shelf::{Shelf, ShelfItem};
+    use crate::subjects::Subject;
+    use tempfile;
+
+    #[test]
+    fn shelf_index_usage() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let mut shelf = Shelf::from(tmp_dir.path())?;
+        shelf.open_index()?;
+
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+        let note = Note::new("Taylor Series");
+        fs::write(
+            note.path_in_shelf((&subject, &shelf)),
+            "%% lanoma: tags = [\"exam-2\"]\n",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            subject.metadata_path_in_shelf(&shelf),
+            "name = \"Calculus I\"",
+        )
+        .map_err(Error::IoError)?;
+
+        let notes = subject.get_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        subject.get_config(&shelf)?;
+        shelf.save_index()?;
+        assert_eq!(notes.len(), 1);
+        assert!(ShelfIndex::path(shelf.path()).is_file());
+
+        let index = ShelfIndex::open(shelf.path(), shelf.slug())?;
+        let stamp = FileStamp::from_path(note.path_in_shelf((&subject, &shelf))).unwrap();
+        let indexed_note = index.note(note.path(&subject), stamp).unwrap();
+        assert_eq!(indexed_note.tags(), vec!["exam-2"]);
+        assert_eq!(index.subjects().len(), 1);
+
+        // The files of the unchanged subject folder are listed from the index.
+        let globs = vec![String::from("*.tex")];
+        let folder_stamp = FileStamp::from_path(subject.path_in_shelf(&shelf)).unwrap();
+        assert_eq!(
+            index.listing(subject.path(), folder_stamp, &globs),
+            Some(&vec![note.file_name()])
+        );
+        assert!(index
+            .listing(subject.path(), folder_stamp, &[String::from("*.md")])
+            .is_none());
+
+        // Adding a file changes the folder so it is searched again.
+        Note::new("Power Series").export((&subject, &shelf))?;
+        let folder_stamp = FileStamp::from_path(subject.path_in_shelf(&shelf)).unwrap();
+        assert!(index
+            .listing(subject.path(), folder_stamp, &globs)
+            .is_none());
+        assert_eq!(subject.get_notes_in_fs(&globs, &shelf)?.len(), 2);
+        Note::new("Power Series").delete((&subject, &shelf))?;
+
+        // Changing the file invalidates the entry.
+        fs::write(
+            note.path_in_shelf((&subject, &shelf)),
+            "%% lanoma: tags = [\"exam-1\", \"proofs\"]\n",
+        )
+        .map_err(Error::IoError)?;
+        let stamp = FileStamp::from_path(note.path_in_shelf((&subject, &shelf))).unwrap();
+        assert!(index.note(note.path(&subject), stamp).is_none());
+        let notes = subject.get_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        assert_eq!(notes[0].tags(), vec!["exam-1", "proofs"]);
+
+        // Deleted notes are removed from the index on the next search.
+        note.delete((&subject, &shelf))?;
+        subject.get_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        assert!(shelf.index().unwrap().notes().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn shelf_reindex() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+
+        let subjects = [Subject::new("Calculus"), Subject::new("Calculus/Limits")];
+        for subject in subjects.iter() {
+            subject.export(&shelf)?;
+            Note::new("Introduction").export((subject, &shelf))?;
+        }
+        fs::create_dir_all(tmp_dir.path().join(INDEX_FOLDER)).map_err(Error::IoError)?;
+        fs::write(ShelfIndex::path(shelf.path()), "invalid = ").map_err(Error::IoError)?;
+        assert!(ShelfIndex::open(shelf.path(), shelf.slug()).is_err());
+
+        shelf.reindex()?;
+        let mut index = ShelfIndex::open(shelf.path(), shelf.slug())?;
+        assert_eq!(index.notes().len(), 2);
+        assert!(shelf.index().is_none());
+
+        // The index is made again for a shelf with a different slug strategy.
+        assert!(ShelfIndex::open(shelf.path(), SlugStrategy::Ascii).is_err());
+
+        index.remove_subject(subjects[1].path());
+        assert_eq!(index.notes().len(), 1);
+        assert!(index.is_changed());
+
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+// The errors of the template engines are kept as they are which makes the `Err` variant of the results large.
+// Boxing them is not worth it for the errors that are mostly reported right before exiting.
+#![allow(clippy::result_large_err)]
+
 use std::result;
 
 #[macro_use]
@@ -8,14 +12,19 @@ use toml::{self};
 #[macro_use]
 extern crate lazy_static;
 
+pub mod archetype;
+pub mod compile;
 pub mod config;
 mod consts;
 pub mod error;
 mod helpers;
+pub mod hooks;
+pub mod index;
 pub mod masternote;
 pub mod note;
 pub mod profile;
 pub mod shelf;
+pub mod slug;
 pub mod subjects;
 pub mod templates;
 
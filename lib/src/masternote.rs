@@ -1,13 +1,19 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::fs::OpenOptions;
+use std::fs::{self, OpenOptions};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use toml;
 
+use crate::config::{MasterConfig, NoteSort, SubjectConfig};
 use crate::error::Error;
-use crate::note::Note;
+use crate::note::{Note, TagFilter};
+use crate::profile::{Profile, PROFILE_MASTER_NOTE_TEMPLATE_NAME};
 use crate::shelf::{Shelf, ShelfData, ShelfItem};
 use crate::subjects::Subject;
+use crate::templates::TemplateRegistry;
 use crate::{Object, Result};
 
 use crate::modify_toml_table;
@@ -15,9 +21,12 @@ use crate::modify_toml_table;
 const MASTER_NOTE_FILE: &str = "_master.tex";
 
 /// The master note is a note that uses the filtered notes.
+#[derive(Clone, Debug)]
 pub struct MasterNote {
     subject: Subject,
     notes: Vec<Note>,
+    parts: Vec<MasterNote>,
+    file_name: String,
 }
 
 impl Object for MasterNote {
@@ -25,9 +34,11 @@ impl Object for MasterNote {
         let mut master_note_as_toml = toml::Value::from(HashMap::<String, toml::Value>::new());
         let notes_toml: Vec<toml::Value> =
             self.notes.iter().map(|note| Object::data(note)).collect();
+        let parts_toml: Vec<toml::Value> = self.parts.iter().map(Object::data).collect();
 
         modify_toml_table! {master_note_as_toml,
             ("notes", notes_toml),
+            ("parts", parts_toml),
             ("subject", Object::data(&self.subject)),
             ("_file", self.file_name())
         };
@@ -50,6 +61,7 @@ impl ShelfData<&Shelf> for MasterNote {
 
         modify_toml_table! {master_note_as_toml,
             ("notes", notes_toml),
+            ("parts", self.parts_data(shelf, 1)),
             ("path", self.path().to_string_lossy())
         };
 
@@ -57,6 +69,30 @@ impl ShelfData<&Shelf> for MasterNote {
     }
 }
 
+impl MasterNote {
+    /// Returns the data of the parts with their `level` in the hierarchy starting from 1.
+    /// The parts have the full subject data so the templates can use the metadata of the child subjects.
+    fn parts_data(
+        &self,
+        shelf: &Shelf,
+        level: i64,
+    ) -> Vec<toml::Value> {
+        self.parts
+            .iter()
+            .map(|part| {
+                let mut part_as_toml = ShelfData::data(part, shelf);
+                modify_toml_table! {part_as_toml,
+                    ("subject", ShelfData::data(part.subject(), shelf)),
+                    ("parts", part.parts_data(shelf, level + 1)),
+                    ("level", level)
+                };
+
+                part_as_toml
+            })
+            .collect()
+    }
+}
+
 impl ShelfItem<&Shelf> for MasterNote {
     /// Return the path of the master note in the shelf.
     fn path_in_shelf(
@@ -64,7 +100,7 @@ impl ShelfItem<&Shelf> for MasterNote {
         shelf: &Shelf,
     ) -> PathBuf {
         let mut path = self.subject.path_in_shelf(&shelf);
-        path.push(MASTER_NOTE_FILE);
+        path.push(&self.file_name);
 
         path
     }
@@ -96,9 +132,28 @@ impl MasterNote {
         Self {
             subject,
             notes: Vec::new(),
+            parts: Vec::new(),
+            file_name: MASTER_NOTE_FILE.to_string(),
         }
     }
 
+    /// Create a new master note with the given name.
+    /// Named master notes have a file name of `_master-{name}.tex` by default with the slug strategy of the subject.
+    /// The file extension can be changed with the master note configuration of the subject.
+    pub fn with_name<S>(
+        subject: Subject,
+        name: S,
+    ) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let file_name = format!("_master-{}.tex", subject.slug().slugify(name));
+        let mut master_note = Self::new(subject);
+        master_note.set_file_name(file_name);
+
+        master_note
+    }
+
     /// Return a reference to the subject.
     pub fn subject(&self) -> &Subject {
         &self.subject
@@ -118,16 +173,761 @@ impl MasterNote {
         self
     }
 
+    /// Return a reference to the parts (i.e., the master notes of the child subjects).
+    pub fn parts(&self) -> &Vec<MasterNote> {
+        &self.parts
+    }
+
+    /// Add the master note of a child subject as a part.
+    pub fn push_part(
+        &mut self,
+        part: MasterNote,
+    ) -> &mut Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Checks if the master note has no notes including its parts.
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty() && self.parts.iter().all(|part| part.is_empty())
+    }
+
     /// Return the path of the master note.
     pub fn path(&self) -> PathBuf {
         let mut path = self.subject.path();
-        path.push(MASTER_NOTE_FILE);
+        path.push(&self.file_name);
 
         path
     }
 
     /// Return the file name of the master note.
     pub fn file_name(&self) -> String {
-        MASTER_NOTE_FILE.to_string()
+        self.file_name.clone()
+    }
+
+    /// Set the file name of the master note.
+    pub fn set_file_name<S>(
+        &mut self,
+        file_name: S,
+    ) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.file_name = file_name.as_ref().to_string();
+        self
+    }
+
+    /// Return the file extension of the master note.
+    pub fn extension(&self) -> String {
+        Path::new(&self.file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Set the file extension of the master note while keeping the file stem.
+    pub fn set_extension<S>(
+        &mut self,
+        extension: S,
+    ) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        let file_name = Path::new(&self.file_name)
+            .with_extension(extension.as_ref().trim_start_matches('.'))
+            .to_string_lossy()
+            .to_string();
+
+        self.set_file_name(file_name)
+    }
+}
+
+/// A builder for generating the master notes of the subjects in a shelf.
+///
+/// Generating a master note involves collecting the filtered notes of the subject,
+/// rendering the master note template, and writing the result into the shelf.
+pub struct MasterNoteGenerator {
+    shelf: Shelf,
+    files: Option<Vec<String>>,
+    template: Option<String>,
+    recursive: bool,
+    name: Option<String>,
+    tag_filter: Option<TagFilter>,
+
+    /// The errors of the notes that are skipped while collecting the notes of the subjects.
+    skipped: Mutex<Vec<Error>>,
+}
+
+/// The result from generating the master notes of multiple subjects.
+pub struct MasterNoteResult {
+    /// The master notes that are successfully written in the shelf.
+    pub generated: Vec<MasterNote>,
+
+    /// The subjects that failed to generate a master note along with the reason.
+    pub failed: Vec<(Subject, Error)>,
+
+    /// The notes that are left out of the master notes along with the reason (e.g., an invalid front matter).
+    pub skipped: Vec<Error>,
+}
+
+impl Default for MasterNoteResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MasterNoteResult {
+    pub fn new() -> Self {
+        Self {
+            generated: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+impl MasterNoteGenerator {
+    /// Creates a new generator for the given shelf.
+    pub fn new(shelf: &Shelf) -> Self {
+        Self {
+            shelf: shelf.clone(),
+            files: None,
+            template: None,
+            recursive: false,
+            name: None,
+            tag_filter: None,
+            skipped: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the file globs of the notes to be included.
+    /// This overrides the file globs from the subject metadata.
+    pub fn files(
+        &mut self,
+        files: Vec<String>,
+    ) -> &mut Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// Set the tag filter of the notes to be included.
+    /// This overrides the tags from the named master note configuration.
+    pub fn tag_filter(
+        &mut self,
+        tag_filter: TagFilter,
+    ) -> &mut Self {
+        self.tag_filter = Some(tag_filter);
+        self
+    }
+
+    /// Set the name of the template to be used for rendering the master notes.
+    /// This overrides the template from the named master note configuration.
+    pub fn template<S>(
+        &mut self,
+        template: S,
+    ) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.template = Some(template.as_ref().to_string());
+        self
+    }
+
+    /// Set whether to include the notes of the child subjects as parts of the master note.
+    pub fn recursive(
+        &mut self,
+        recursive: bool,
+    ) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Set the name of the master note to be generated.
+    /// The named master note is configured in the `masters` table of the subject metadata.
+    pub fn name<S>(
+        &mut self,
+        name: S,
+    ) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// Returns the configuration of the master note of the subject.
+    ///
+    /// It will return an error if the subject has no master note with the name.
+    pub fn master_config(
+        &self,
+        subject: &Subject,
+    ) -> Result<MasterConfig> {
+        let subject_config = subject.get_config(&self.shelf).unwrap_or_default();
+
+        subject_config.master(self.name.as_deref()).ok_or_else(|| {
+            Error::UnknownMasterNoteError(
+                subject.path_in_shelf(&self.shelf),
+                self.name.clone().unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Creates the master note instance of the subject with the filtered notes.
+    /// In recursive mode, the child subjects are included as parts.
+    ///
+    /// It will return an error if the subject has no matching notes.
+    pub fn create(
+        &self,
+        subject: &Subject,
+    ) -> Result<MasterNote> {
+        let master_config = self.master_config(subject)?;
+        let master_note = self.collect(subject, &master_config)?;
+        if master_note.is_empty() {
+            return Err(Error::EmptyMasterNoteError(
+                master_note.path_in_shelf(&self.shelf),
+            ));
+        }
+
+        Ok(master_note)
+    }
+
+    /// Returns the notes of the subject in the same order as they appear in its master note.
+    /// The given note is included in the order even if it is not in the shelf yet (e.g., a note being created).
+    pub fn notes(
+        &self,
+        subject: &Subject,
+        note: Option<&Note>,
+    ) -> Result<Vec<Note>> {
+        let master_config = self.master_config(subject)?;
+
+        self.filtered_notes(subject, &master_config, note)
+    }
+
+    /// Filters and orders the notes of the subject with the master note configuration.
+    fn filtered_notes(
+        &self,
+        subject: &Subject,
+        master_config: &MasterConfig,
+        note: Option<&Note>,
+    ) -> Result<Vec<Note>> {
+        let files = self
+            .files
+            .as_ref()
+            .or(master_config.files.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        let (mut notes, skipped) = subject.search_notes_in_fs(&files, &self.shelf)?;
+        if let Ok(mut skipped_notes) = self.skipped.lock() {
+            skipped_notes.extend(skipped);
+        }
+        if let Some(note) = note {
+            if !notes
+                .iter()
+                .any(|other| other.title() == note.title() || other.stem() == note.stem())
+            {
+                notes.push(note.clone());
+            }
+        }
+
+        let notes = self
+            .tag_filter
+            .as_ref()
+            .unwrap_or(&master_config.tag_filter)
+            .filter(notes);
+
+        Ok(self.order_notes(notes, subject, master_config))
+    }
+
+    /// Collects the notes (and the parts in recursive mode) of the subject.
+    fn collect(
+        &self,
+        subject: &Subject,
+        master_config: &MasterConfig,
+    ) -> Result<MasterNote> {
+        let notes = self.filtered_notes(subject, master_config, None)?;
+
+        let mut master_note = self.empty_master_note(subject, master_config);
+        for note in notes {
+            master_note.push(&note);
+        }
+
+        if self.recursive {
+            for child_subject in subject.get_subjects_in_fs(&self.shelf)? {
+                // The child subjects without the named master note use their default configuration.
+                let child_config = match self.master_config(&child_subject) {
+                    Ok(config) => config,
+                    Err(_e) => child_subject
+                        .get_config(&self.shelf)
+                        .unwrap_or(SubjectConfig::new())
+                        .master(None)
+                        .unwrap_or_default(),
+                };
+                let part = self.collect(&child_subject, &child_config)?;
+
+                if !part.is_empty() {
+                    master_note.push_part(part);
+                }
+            }
+        }
+
+        Ok(master_note)
+    }
+
+    /// Creates a master note of the subject without the notes with the file name from the configuration.
+    fn empty_master_note(
+        &self,
+        subject: &Subject,
+        master_config: &MasterConfig,
+    ) -> MasterNote {
+        let mut master_note = match &self.name {
+            Some(name) => MasterNote::with_name(subject.clone(), name),
+            None => MasterNote::new(subject.clone()),
+        };
+        if let Some(extension) = &master_config.extension {
+            master_note.set_extension(extension);
+        }
+        if let Some(file_name) = &master_config.file {
+            master_note.set_file_name(file_name);
+        }
+
+        master_note
+    }
+
+    /// Opens the existing master note of the subject without regenerating it.
+    /// The resulting master note does not have the notes.
+    ///
+    /// It will return an error if the master note does not exist in the shelf.
+    pub fn open(
+        &self,
+        subject: &Subject,
+    ) -> Result<MasterNote> {
+        let master_note = self.empty_master_note(subject, &self.master_config(subject)?);
+        if !master_note.is_item_valid(&self.shelf) {
+            return Err(Error::MissingMasterNoteError(
+                master_note.path_in_shelf(&self.shelf),
+            ));
+        }
+
+        Ok(master_note)
+    }
+
+    /// Orders the notes with the sorting method and the explicit order from the master note configuration.
+    fn order_notes(
+        &self,
+        mut notes: Vec<Note>,
+        subject: &Subject,
+        master_config: &MasterConfig,
+    ) -> Vec<Note> {
+        // The notes without a file (i.e., not in the shelf yet) are the newest ones.
+        match master_config.sort.unwrap_or_default() {
+            NoteSort::Title => notes.sort_by_cached_key(|note| note.title().to_lowercase()),
+            NoteSort::Modified => notes.sort_by_cached_key(|note| {
+                let modified = fs::metadata(note.path_in_shelf((subject, &self.shelf)))
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+
+                (modified.is_none(), modified)
+            }),
+            NoteSort::Created => notes.sort_by_cached_key(|note| {
+                let created = fs::metadata(note.path_in_shelf((subject, &self.shelf)))
+                    .and_then(|metadata| metadata.created().or_else(|_e| metadata.modified()))
+                    .ok();
+
+                (created.is_none(), created)
+            }),
+        }
+
+        if master_config.order.is_empty() {
+            return notes;
+        }
+
+        // The entries of the order can either be the title or the file name of the note.
+        let mut ordered_notes: Vec<Note> = vec![];
+        for entry in master_config.order.iter() {
+            let stem = Note::new(entry).set_slug(self.shelf.slug()).stem();
+
+            if let Some(index) = notes
+                .iter()
+                .position(|note| note.file_name() == *entry || note.stem() == stem)
+            {
+                ordered_notes.push(notes.remove(index));
+            }
+        }
+
+        if !master_config.exclude_unordered.unwrap_or_default() {
+            ordered_notes.append(&mut notes);
+        }
+
+        ordered_notes
+    }
+
+    /// Renders the master note with the template from the profile.
+    pub fn render(
+        &self,
+        master_note: &MasterNote,
+        profile: &Profile,
+    ) -> Result<String> {
+        let template = match &self.template {
+            Some(template) => template.clone(),
+            None => self
+                .master_config(master_note.subject())?
+                .template
+                .unwrap_or_else(|| {
+                    profile.default_template_name(
+                        PROFILE_MASTER_NOTE_TEMPLATE_NAME,
+                        master_note.extension(),
+                    )
+                }),
+        };
+
+        let mut object = toml::Value::from(HashMap::<String, toml::Value>::new());
+        modify_toml_table! {object,
+            ("profile", Object::data(profile)),
+            ("subject", ShelfData::data(master_note.subject(), &self.shelf)),
+            ("master", ShelfData::data(master_note, &self.shelf)),
+            ("shelf", Object::data(&self.shelf))
+        };
+
+        profile.template_registry().render(&template, &object)
+    }
+
+    /// Generates the master note of the subject.
+    /// The resulting master note file overwrites the existing file.
+    pub fn generate_one(
+        &self,
+        subject: &str,
+        profile: &Profile,
+    ) -> Result<MasterNote> {
+        let subject = Subject::from_shelf(subject, &self.shelf)?;
+        let master_note = self.create(&subject)?;
+        let content = self.render(&master_note, profile)?;
+
+        fs::write(master_note.path_in_shelf(&self.shelf), content).map_err(Error::IoError)?;
+
+        Ok(master_note)
+    }
+
+    /// Generates the master notes of the subjects in parallel.
+    /// A failure from one of the subjects does not stop the generation of the rest.
+    /// The notes that cannot be read are left out of the master notes and reported in the result.
+    pub fn generate<S>(
+        &self,
+        subjects: &[S],
+        profile: &Profile,
+    ) -> MasterNoteResult
+    where
+        S: AsRef<str> + Sync,
+    {
+        let results: Vec<(Subject, Result<MasterNote>)> = subjects
+            .par_iter()
+            .map(|subject| {
+                let subject = subject.as_ref();
+
+                let mut subject_key = Subject::new(subject);
+                subject_key.set_slug(self.shelf.slug());
+
+                (subject_key, self.generate_one(subject, profile))
+            })
+            .collect();
+
+        let mut master_note_result = MasterNoteResult::new();
+        for (subject, result) in results {
+            match result {
+                Ok(master_note) => master_note_result.generated.push(master_note),
+                Err(error) => master_note_result.failed.push((subject, error)),
+            }
+        }
+        if let Ok(mut skipped_notes) = self.skipped.lock() {
+            master_note_result.skipped = mem::take(&mut *skipped_notes);
+        }
+
+        master_note_result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile;
+
+    #[test]
+    fn master_note_generation() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let profile = Profile::default();
+
+        let calculus = Subject::new("Calculus");
+        calculus.export(&shelf)?;
+        Subject::new("Algebra").export(&shelf)?;
+        for note in &["Introduction to Limits", "Taylor Series"] {
+            let note = Note::new(note);
+            fs::write(note.path_in_shelf((&calculus, &shelf)), "").map_err(Error::IoError)?;
+        }
+
+        let result = MasterNoteGenerator::new(&shelf)
+            .generate(&["Calculus", "Algebra", "Physics"], &profile);
+
+        assert_eq!(result.generated.len(), 1);
+        assert_eq!(result.generated[0].notes().len(), 2);
+        assert!(result.generated[0].is_item_valid(&shelf));
+
+        let mut failed = result
+            .failed
+            .iter()
+            .map(|(subject, error)| (subject.name(), error));
+        match failed.next() {
+            Some((name, Error::EmptyMasterNoteError(_))) => assert_eq!(name, "Algebra"),
+            _ => panic!("The empty subject should fail."),
+        }
+        match failed.next() {
+            Some((name, Error::InvalidSubjectError(_))) => assert_eq!(name, "Physics"),
+            _ => panic!("The nonexistent subject should fail."),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn master_note_ordering() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+
+        let calculus = Subject::new("Calculus");
+        calculus.export(&shelf)?;
+        for note in &[
+            "Taylor Series",
+            "Derivatives",
+            "Introduction to Limits",
+            "Integrals",
+        ] {
+            let note = Note::new(note);
+            fs::write(note.path_in_shelf((&calculus, &shelf)), "").map_err(Error::IoError)?;
+        }
+
+        let generator = MasterNoteGenerator::new(&shelf);
+        let titles = |master_note: MasterNote| -> Vec<String> {
+            master_note.notes().iter().map(|note| note.stem()).collect()
+        };
+
+        assert_eq!(
+            titles(generator.create(&calculus)?),
+            vec![
+                "derivatives",
+                "integrals",
+                "introduction-to-limits",
+                "taylor-series"
+            ]
+        );
+
+        let metadata_path = calculus.metadata_path_in_shelf(&shelf);
+        fs::write(
+            &metadata_path,
+            "order = [\"Introduction to Limits\", \"derivatives.tex\"]",
+        )
+        .map_err(Error::IoError)?;
+        assert_eq!(
+            titles(generator.create(&calculus)?),
+            vec![
+                "introduction-to-limits",
+                "derivatives",
+                "integrals",
+                "taylor-series"
+            ]
+        );
+
+        fs::write(
+            &metadata_path,
+            "order = [\"Introduction to Limits\", \"derivatives.tex\"]\nexclude_unordered = true",
+        )
+        .map_err(Error::IoError)?;
+        assert_eq!(
+            titles(generator.create(&calculus)?),
+            vec!["introduction-to-limits", "derivatives"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_master_note() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let profile = Profile::default();
+
+        let year = Subject::new("Year 1");
+        let calculus = Subject::new("Year 1/Semester 1/Calculus");
+        let physics = Subject::new("Year 1/Semester 2/Physics");
+        for subject in &[&calculus, &physics, &Subject::new("Year 1/Semester 3")] {
+            for part in subject.split_subjects().iter().rev() {
+                part.export(&shelf)?;
+                part.embed_name(&shelf)?;
+            }
+        }
+        for subject in &[&calculus, &physics] {
+            let note = Note::new("Introduction");
+            fs::write(note.path_in_shelf((subject, &shelf)), "").map_err(Error::IoError)?;
+        }
+
+        let mut generator = MasterNoteGenerator::new(&shelf);
+        assert!(generator.create(&year).is_err());
+
+        generator.recursive(true);
+        let master_note = generator.generate_one("Year 1", &profile)?;
+        let semesters: Vec<String> = master_note
+            .parts()
+            .iter()
+            .map(|part| part.subject().name())
+            .collect();
+
+        assert!(master_note.notes().is_empty());
+        assert_eq!(semesters, vec!["Semester 1", "Semester 2"]);
+        assert_eq!(master_note.parts()[0].parts()[0].notes().len(), 1);
+
+        // The notes of the nested parts are rendered in the master note.
+        let content =
+            fs::read_to_string(master_note.path_in_shelf(&shelf)).map_err(Error::IoError)?;
+        assert!(content.contains("\\chapter{Semester 1}"));
+        assert!(content.contains("\\section{Calculus}"));
+        assert_eq!(content.matches("Note: introduction").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn typst_master_note() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let profile = Profile::default();
+
+        let calculus = Subject::new("Calculus");
+        let limits = Subject::new("Calculus/Limits");
+        for (subject, note) in &[(&calculus, "Taylor Series"), (&limits, "Introduction")] {
+            subject.export(&shelf)?;
+            fs::write(
+                subject.path_in_shelf(&shelf).join("info.toml"),
+                "engine = 'typst'",
+            )
+            .map_err(Error::IoError)?;
+
+            let mut note = Note::new(note);
+            note.set_extension("typ");
+            fs::write(note.path_in_shelf((*subject, &shelf)), "").map_err(Error::IoError)?;
+        }
+
+        let mut generator = MasterNoteGenerator::new(&shelf);
+        generator.recursive(true);
+        let master_note = generator.generate_one("Calculus", &profile)?;
+        assert_eq!(master_note.file_name(), "_master.typ");
+
+        let content =
+            fs::read_to_string(master_note.path_in_shelf(&shelf)).map_err(Error::IoError)?;
+        assert!(content.contains("#include \"taylor-series.typ\""));
+        assert!(content.contains("#include \"limits/introduction.typ\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn named_master_notes() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let profile = Profile::default();
+
+        let calculus = Subject::new("Calculus");
+        calculus.export(&shelf)?;
+        for note in &["Week 1", "Week 2", "Week 3"] {
+            let note = Note::new(note);
+            fs::write(note.path_in_shelf((&calculus, &shelf)), "").map_err(Error::IoError)?;
+        }
+        fs::write(
+            calculus.metadata_path_in_shelf(&shelf),
+            r#"
+            [masters.midterm]
+            _files = ["week-1.tex", "week-2.tex"]
+            order = ["Week 2"]
+
+            [masters.finals]
+            file = "finals.tex"
+
+            [masters.review]
+            tags = ["exam-2"]
+            exclude_tags = ["todo"]
+            "#,
+        )
+        .map_err(Error::IoError)?;
+
+        let mut generator = MasterNoteGenerator::new(&shelf);
+        generator.name("midterm");
+        let midterm = generator.generate_one("Calculus", &profile)?;
+        let midterm_notes: Vec<String> = midterm.notes().iter().map(|note| note.stem()).collect();
+
+        assert_eq!(midterm.file_name(), "_master-midterm.tex");
+        assert_eq!(midterm_notes, vec!["week-2", "week-1"]);
+        assert!(midterm.is_item_valid(&shelf));
+
+        generator.name("finals");
+        let finals = generator.generate_one("Calculus", &profile)?;
+
+        assert_eq!(finals.file_name(), "finals.tex");
+        assert_eq!(finals.notes().len(), 3);
+
+        fs::write(
+            Note::new("Week 2").path_in_shelf((&calculus, &shelf)),
+            "%% lanoma: tags = [\"exam-2\"]\n",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            Note::new("Week 3").path_in_shelf((&calculus, &shelf)),
+            "%% lanoma: tags = [\"exam-2\", \"todo\"]\n",
+        )
+        .map_err(Error::IoError)?;
+        generator.name("review");
+        let review = generator.generate_one("Calculus", &profile)?;
+        let review_notes: Vec<String> = review.notes().iter().map(|note| note.stem()).collect();
+
+        assert_eq!(review_notes, vec!["week-2"]);
+
+        generator.tag_filter(TagFilter::new(vec!["todo".to_string()], vec![]));
+        let review = generator.generate_one("Calculus", &profile)?;
+
+        assert_eq!(review.notes().len(), 1);
+        assert_eq!(review.notes()[0].stem(), "week-3");
+
+        generator.name("quiz");
+        assert!(match generator.generate_one("Calculus", &profile) {
+            Err(Error::UnknownMasterNoteError(_, name)) => name == "quiz",
+            _ => false,
+        });
+
+        generator.name("midterm");
+        assert_eq!(
+            generator.open(&calculus)?.file_name(),
+            "_master-midterm.tex"
+        );
+        assert!(MasterNoteGenerator::new(&shelf).open(&calculus).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn master_note_generation_with_invalid_template() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let profile = Profile::default();
+
+        let calculus = Subject::new("Calculus");
+        calculus.export(&shelf)?;
+        let note = Note::new("Taylor Series");
+        fs::write(note.path_in_shelf((&calculus, &shelf)), "").map_err(Error::IoError)?;
+
+        let mut generator = MasterNoteGenerator::new(&shelf);
+        generator.template("this-template-does-not-exist");
+
+        assert!(matches!(
+            generator.generate_one("Calculus", &profile),
+            Err(Error::HandlebarsRenderError(_))
+        ));
+
+        Ok(())
     }
 }
@@ -1,17 +1,29 @@
-use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::path::PathBuf;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
 
-use heck::KebabCase;
 use serde::{Deserialize, Serialize};
 use toml;
 
-use crate::shelf::{Shelf, ShelfData, ShelfItem};
 use crate::error::Error;
+use crate::shelf::{Shelf, ShelfData, ShelfItem};
+use crate::slug::SlugStrategy;
 use crate::subjects::Subject;
 use crate::{Object, Result};
 
-use crate::modify_toml_table;
+use crate::{modify_toml_table, upsert_toml_table};
+
+/// The prefix of the comment lines containing the front matter of the note.
+pub const FRONT_MATTER_PREFIX: &str = "%% lanoma:";
+
+/// The default file extension of the notes.
+pub const DEFAULT_NOTE_EXTENSION: &str = "tex";
+
+/// The comment syntax (i.e., the prefix and the suffix) of the front matter lines in other document formats.
+/// The notes with the file extensions not listed here use the LaTeX comments.
+const FRONT_MATTER_COMMENTS: &[(&[&str], &str, &str)] = &[
+    (&["md", "markdown", "rmd", "html"], "<!-- lanoma:", " -->"),
+    (&["typ"], "// lanoma:", ""),
+];
 
 /// The individual LaTeX documents in a notes instance.
 ///
@@ -23,6 +35,23 @@ use crate::modify_toml_table;
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Note {
     title: String,
+
+    /// The file name (without the extension) of the note if it is different from the slug of the title.
+    /// This is mainly used for notes with their title from the front matter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stem: Option<String>,
+
+    /// The file extension of the note if it is not the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extension: Option<String>,
+
+    /// The metadata from the front matter of the note.
+    #[serde(default)]
+    metadata: toml::value::Table,
+
+    /// The slug strategy for the file name of the note, taken from the shelf.
+    #[serde(skip)]
+    slug: SlugStrategy,
 }
 
 impl AsRef<str> for Note {
@@ -74,10 +103,16 @@ impl ShelfItem<(&Subject, &Shelf)> for Note {
 
 impl Object for Note {
     fn data(&self) -> toml::Value {
-        let mut note_as_toml = toml::Value::from(HashMap::<String, toml::Value>::new());
+        // The generated keys take precedence over the front matter except for the title.
+        let mut note_as_toml = toml::Value::from(self.metadata.clone());
+        upsert_toml_table! {note_as_toml,
+            ("title", self.title())
+        };
         modify_toml_table! {note_as_toml,
-            ("title", self.title()),
-            ("file", self.file_name())
+            ("file", self.file_name()),
+            ("stem", self.stem()),
+            ("extension", self.extension()),
+            ("front_matter", self.front_matter())
         };
 
         note_as_toml
@@ -107,24 +142,89 @@ impl Note {
     {
         Self {
             title: title.as_ref().to_string(),
+            stem: None,
+            extension: None,
+            metadata: toml::value::Table::new(),
+            slug: SlugStrategy::default(),
         }
     }
 
     /// Searches for the note in the shelf filesystem.
+    /// The metadata is also read from the front matter of the note, if it's valid.
+    ///
+    /// The note is searched with the file extension of the subject first then with any file extension.
     pub fn from<S: AsRef<str>>(
         title: S,
         subject: &Subject,
         shelf: &Shelf,
     ) -> Option<Self> {
         let title = title.as_ref();
-        let note = Note::new(title.to_string());
+        let mut note = Note::new(title);
+        note.set_slug(shelf.slug());
+        if let Ok(subject_config) = subject.get_config(shelf) {
+            note.set_extension(subject_config.extension());
+        }
+
+        if !note.is_item_valid((subject, shelf)) {
+            let stem = note.stem();
+            let file_name = fs::read_dir(subject.path_in_shelf(shelf))
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .file_stem()
+                            .map(|s| s.to_string_lossy() == stem)
+                            .unwrap_or(false)
+                })
+                .find_map(|path| {
+                    path.extension()
+                        .map(|extension| extension.to_string_lossy().to_string())
+                });
+
+            note.set_extension(file_name?);
+        }
 
         match note.is_item_valid((&subject, &shelf)) {
-            true => Some(note),
+            true => {
+                note.read_metadata((subject, shelf)).ok();
+                Some(note)
+            }
             false => None,
         }
     }
 
+    /// Opens the note from its file in the subject folder with the file name.
+    ///
+    /// The file name (without the extension) identifies the note while the title from the front matter is only for display.
+    /// The files starting with an underscore (e.g., `_master.tex`) are reserved for the master notes so they are not notes.
+    pub fn from_file<S: AsRef<str>>(
+        file_name: S,
+        subject: &Subject,
+        shelf: &Shelf,
+    ) -> Result<Option<Self>> {
+        let file_name = Path::new(file_name.as_ref());
+        let file_stem = match file_name.file_stem() {
+            Some(file_stem) => file_stem.to_string_lossy().to_string(),
+            None => return Ok(None),
+        };
+        let mut note = Note::new(&file_stem);
+        note.set_slug(shelf.slug());
+        note.stem = Some(file_stem.clone());
+        note.extension = file_name
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string());
+
+        if file_stem.starts_with('_') || !note.is_item_valid((subject, shelf)) {
+            return Ok(None);
+        }
+
+        note.read_metadata((subject, shelf))?;
+
+        Ok(Some(note))
+    }
+
     /// Similar to the `from` method, only on a bigger scale.
     pub fn from_vec<S: AsRef<str>>(
         notes: &Vec<S>,
@@ -147,7 +247,13 @@ impl Note {
     ) -> Vec<Self> {
         notes
             .iter()
-            .map(|note| Self::from(note, &subject, &shelf).unwrap_or(Self::new(note)))
+            .map(|note| {
+                Self::from(note, subject, shelf).unwrap_or_else(|| {
+                    let mut note = Self::new(note);
+                    note.set_slug(shelf.slug());
+                    note
+                })
+            })
             .collect()
     }
 
@@ -156,6 +262,188 @@ impl Note {
         self.title.clone()
     }
 
+    /// Returns the file extension of the note.
+    pub fn extension(&self) -> String {
+        self.extension
+            .clone()
+            .unwrap_or(DEFAULT_NOTE_EXTENSION.to_string())
+    }
+
+    /// Returns the slug strategy of the note.
+    pub fn slug(&self) -> SlugStrategy {
+        self.slug
+    }
+
+    /// Sets the slug strategy for the file name of the note.
+    /// The notes made with a shelf (e.g., `Note::from`) already have the slug strategy of the shelf.
+    pub fn set_slug(
+        &mut self,
+        slug: SlugStrategy,
+    ) -> &mut Self {
+        self.slug = slug;
+        self
+    }
+
+    /// Set the file extension of the note.
+    pub fn set_extension<S: AsRef<str>>(
+        &mut self,
+        extension: S,
+    ) -> &mut Self {
+        let extension = extension.as_ref().trim_start_matches('.');
+
+        self.extension = match extension == DEFAULT_NOTE_EXTENSION {
+            true => None,
+            false => Some(extension.to_string()),
+        };
+        self
+    }
+
+    /// Returns the metadata from the front matter of the note.
+    pub fn metadata(&self) -> &toml::value::Table {
+        &self.metadata
+    }
+
+    /// Set the metadata of the note.
+    pub fn set_metadata(
+        &mut self,
+        metadata: toml::value::Table,
+    ) -> &mut Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Returns the tags of the note from the `tags` key of the front matter.
+    /// A single string is also accepted as a tag.
+    pub fn tags(&self) -> Vec<String> {
+        match self.metadata.get("tags") {
+            Some(toml::Value::Array(tags)) => tags
+                .iter()
+                .filter_map(toml::Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            Some(toml::Value::String(tag)) => vec![tag.clone()],
+            _ => vec![],
+        }
+    }
+
+    /// Checks if the note has the given tag.
+    pub fn has_tag<S: AsRef<str>>(
+        &self,
+        tag: S,
+    ) -> bool {
+        self.tags().iter().any(|note_tag| note_tag == tag.as_ref())
+    }
+
+    /// Reads the front matter of the note file in the shelf and sets it as the metadata.
+    /// If the front matter has a title, it will be set as the title of the note while keeping the file name.
+    pub fn read_metadata(
+        &mut self,
+        params: (&Subject, &Shelf),
+    ) -> Result<&mut Self> {
+        let path = self.path_in_shelf(params);
+        let content = fs::read_to_string(&path).map_err(Error::IoError)?;
+
+        self.metadata =
+            parse_front_matter(&content).map_err(|e| Error::FrontMatterError(path, e))?;
+        if let Some(title) = self.metadata.get("title").and_then(toml::Value::as_str) {
+            let title = title.to_string();
+
+            self.stem = Some(self.stem());
+            self.title = title;
+        }
+
+        Ok(self)
+    }
+
+    /// Checks if the file of the note is already used by a different note in the shelf.
+    ///
+    /// The existing note is only considered to be different if the title in its front matter is different.
+    /// Without a title in the front matter, the title of the existing note is its file name (like in `Note::from_file`).
+    pub fn check_collision(
+        &self,
+        params: (&Subject, &Shelf),
+    ) -> Result<()> {
+        let path = self.path_in_shelf(params);
+        if !path.is_file() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path).map_err(Error::IoError)?;
+        let existing_title = parse_front_matter(&content)
+            .ok()
+            .and_then(|front_matter| {
+                front_matter
+                    .get("title")
+                    .and_then(toml::Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| self.stem());
+
+        if existing_title != self.title {
+            return Err(Error::SlugCollisionError(
+                path,
+                existing_title,
+                self.title(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Embeds the title in the front matter of the given note content.
+    /// This is needed to preserve the title if it cannot be recovered from the file name.
+    ///
+    /// If the front matter of the content already has a title, it will return the content as it is.
+    pub fn embed_title<S: AsRef<str>>(
+        &self,
+        content: S,
+    ) -> String {
+        let content = content.as_ref();
+        let has_title = match parse_front_matter(content) {
+            Ok(front_matter) => front_matter.contains_key("title"),
+            Err(_e) => false,
+        };
+
+        if has_title || self.title == self.stem() {
+            return content.to_string();
+        }
+
+        let mut title = toml::value::Table::new();
+        title.insert("title".to_string(), toml::Value::from(self.title()));
+
+        format!("{}{}", self.comment_front_matter(&title), content)
+    }
+
+    /// Returns the metadata (with the title) as front matter lines to be written in the note file.
+    /// The front matter lines are comments in the document format of the note.
+    pub fn front_matter(&self) -> String {
+        let mut metadata = self.metadata.clone();
+        metadata
+            .entry("title")
+            .or_insert(toml::Value::from(self.title()));
+
+        self.comment_front_matter(&metadata)
+    }
+
+    /// Writes the table as front matter lines with the comment syntax from the file extension of the note.
+    fn comment_front_matter(
+        &self,
+        table: &toml::value::Table,
+    ) -> String {
+        let extension = self.extension().to_lowercase();
+        let (prefix, suffix) = FRONT_MATTER_COMMENTS
+            .iter()
+            .find(|(extensions, _, _)| extensions.contains(&extension.as_str()))
+            .map(|(_, prefix, suffix)| (*prefix, *suffix))
+            .unwrap_or((FRONT_MATTER_PREFIX, ""));
+
+        toml::to_string(table)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| format!("{} {}{}\n", prefix, line, suffix))
+            .collect()
+    }
+
     /// Returns the path of the note relative to the subject.
     pub fn path(
         &self,
@@ -169,9 +457,308 @@ impl Note {
 
     /// Returns the file name of the note.
     pub fn file_name(&self) -> String {
-        let mut slug = self.title.to_kebab_case();
-        slug.push_str(".tex");
+        format!("{}.{}", self.stem(), self.extension())
+    }
+
+    /// Returns the file name of the note without the file extension.
+    pub fn stem(&self) -> String {
+        match &self.stem {
+            Some(stem) => stem.clone(),
+            None => self.slug.slugify(&self.title),
+        }
+    }
+}
+
+/// A filter for the notes with their tags.
+///
+/// A note passes the filter if it has any of the included tags (if there are any) and none of the excluded tags.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TagFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_tags: Vec<String>,
+}
+
+impl TagFilter {
+    /// Creates a tag filter with the included and excluded tags.
+    pub fn new(
+        tags: Vec<String>,
+        exclude_tags: Vec<String>,
+    ) -> Self {
+        Self { tags, exclude_tags }
+    }
+
+    /// Checks if the filter has no tags, letting all of the notes pass.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.exclude_tags.is_empty()
+    }
+
+    /// Checks if the note passes the filter.
+    pub fn matches(
+        &self,
+        note: &Note,
+    ) -> bool {
+        let is_included = self.tags.is_empty() || self.tags.iter().any(|tag| note.has_tag(tag));
+        let is_excluded = self.exclude_tags.iter().any(|tag| note.has_tag(tag));
+
+        is_included && !is_excluded
+    }
+
+    /// Filters the notes that pass the filter.
+    pub fn filter(
+        &self,
+        notes: Vec<Note>,
+    ) -> Vec<Note> {
+        notes
+            .into_iter()
+            .filter(|note| self.matches(note))
+            .collect()
+    }
+}
+
+/// Parses the front matter from the leading comment lines of the note content.
+///
+/// Only the consecutive comment lines with the prefix at the start of the content are considered to be the front matter.
+/// The comments can be in any of the supported document formats (e.g., `%% lanoma:` for LaTeX, `<!-- lanoma: -->` for Markdown).
+pub fn parse_front_matter<S: AsRef<str>>(
+    content: S
+) -> std::result::Result<toml::value::Table, toml::de::Error> {
+    let mut front_matter = String::new();
+    for line in content.as_ref().lines() {
+        let line = line.trim_start();
+
+        if line.is_empty() && front_matter.is_empty() {
+            continue;
+        }
+
+        match strip_front_matter_comment(line) {
+            Some(front_matter_line) => {
+                front_matter.push_str(front_matter_line.trim());
+                front_matter.push('\n');
+            }
+            None => break,
+        }
+    }
+
+    toml::from_str(&front_matter)
+}
+
+/// Returns the front matter line without the comment syntax.
+/// It will return `None` if the line is not a front matter line.
+fn strip_front_matter_comment(line: &str) -> Option<&str> {
+    if let Some(front_matter_line) = line.strip_prefix(FRONT_MATTER_PREFIX) {
+        return Some(front_matter_line);
+    }
+
+    FRONT_MATTER_COMMENTS
+        .iter()
+        .find_map(|(_, prefix, suffix)| {
+            line.trim_end()
+                .strip_prefix(prefix)
+                .and_then(|front_matter_line| front_matter_line.strip_suffix(suffix.trim_start()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile;
+
+    #[test]
+    fn note_front_matter() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+
+        let note = Note::new("Taylor Series");
+        let content = format!(
+            "\n{}\\documentclass{{article}}\n%% lanoma: lecturer = \"Not included\"\n",
+            note.front_matter()
+        );
+        assert_eq!(
+            note.front_matter(),
+            "%% lanoma: title = \"Taylor Series\"\n"
+        );
+
+        let mut written_note = Note::new("taylor-series");
+        fs::write(
+            written_note.path_in_shelf((&subject, &shelf)),
+            content.replace(
+                "\\documentclass",
+                "%% lanoma: tags = [\"series\"]\n\\documentclass",
+            ),
+        )
+        .map_err(Error::IoError)?;
+        written_note.read_metadata((&subject, &shelf))?;
+
+        let data = Object::data(&written_note);
+        assert_eq!(data["title"].as_str(), Some("Taylor Series"));
+        assert_eq!(data["tags"][0].as_str(), Some("series"));
+        assert_eq!(data["stem"].as_str(), Some("taylor-series"));
+        assert!(data.get("lecturer").is_none());
+
+        fs::write(
+            written_note.path_in_shelf((&subject, &shelf)),
+            "%% lanoma: title = \n",
+        )
+        .map_err(Error::IoError)?;
+        assert!(written_note.read_metadata((&subject, &shelf)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn note_title_round_trip() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+
+        let note = Note::new("L'Hôpital's Rule & Limits");
+        let content = note.embed_title("\\documentclass{article}\n");
+        assert_eq!(note.embed_title(&content), content);
+        fs::write(note.path_in_shelf((&subject, &shelf)), content).map_err(Error::IoError)?;
+
+        let notes = subject.get_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title(), "L'Hôpital's Rule & Limits");
+        assert_eq!(notes[0].stem(), note.stem());
+        assert!(notes[0].is_item_valid((&subject, &shelf)));
+
+        // The file name identifies the note even if the title does not match it.
+        let subject_path = subject.path_in_shelf(&shelf);
+        fs::write(
+            subject_path.join("limits.tex"),
+            "%% lanoma: title = \"Lecture 2: Limits\"\n",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(subject_path.join("_master.tex"), "").map_err(Error::IoError)?;
+        let mut notes = subject.get_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        notes.sort_by_key(|note| note.stem());
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[1].title(), "Lecture 2: Limits");
+        assert_eq!(notes[1].file_name(), "limits.tex");
+
+        let simple_note = Note::new("derivatives");
+        assert_eq!(simple_note.embed_title("content"), "content");
+
+        // The existing note has the same file name only in the kebab case.
+        let colliding_note = Note::new("L'Hôpital's Rule Limits");
+        assert_eq!(colliding_note.stem(), note.stem());
+        assert!(note.check_collision((&subject, &shelf)).is_ok());
+        assert!(match colliding_note.check_collision((&subject, &shelf)) {
+            Err(Error::SlugCollisionError(_, existing, _)) => existing == note.title(),
+            _ => false,
+        });
+
+        // The existing note without a front matter has the title from its file name.
+        fs::write(subject_path.join("integrals.tex"), "").map_err(Error::IoError)?;
+        assert!(Note::new("integrals")
+            .check_collision((&subject, &shelf))
+            .is_ok());
+        assert!(
+            match Note::new("Integrals").check_collision((&subject, &shelf)) {
+                Err(Error::SlugCollisionError(_, existing, _)) => existing == "integrals",
+                _ => false,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn note_extensions() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+        fs::write(
+            subject.path_in_shelf(&shelf).join("info.toml"),
+            "extension = 'md'",
+        )
+        .map_err(Error::IoError)?;
+
+        let mut note = Note::new("Taylor Series");
+        note.set_extension("md");
+        assert_eq!(note.file_name(), "taylor-series.md");
+        assert_eq!(
+            note.front_matter(),
+            "<!-- lanoma: title = \"Taylor Series\" -->\n"
+        );
+        fs::write(
+            note.path_in_shelf((&subject, &shelf)),
+            note.embed_title("# Taylor Series\n"),
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            subject.path_in_shelf(&shelf).join("limits.typ"),
+            "// lanoma: title = \"Limits\"\n= Limits\n",
+        )
+        .map_err(Error::IoError)?;
+
+        let subject_config = subject.get_config(&shelf)?;
+        let notes = subject.get_notes_in_fs(&subject_config.file_globs(), &shelf)?;
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title(), "Taylor Series");
+        assert_eq!(notes[0].extension(), "md");
+
+        // Notes with other file extensions can still be found by their title.
+        let typst_note = Note::from("Limits", &subject, &shelf).unwrap();
+        assert_eq!(typst_note.file_name(), "limits.typ");
+        assert_eq!(typst_note.front_matter(), "// lanoma: title = \"Limits\"\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn note_tags() {
+        let mut note = Note::new("Taylor Series");
+        let mut metadata = toml::value::Table::new();
+        metadata.insert(
+            "tags".to_string(),
+            toml::Value::from(vec!["exam-2", "proofs"]),
+        );
+        note.set_metadata(metadata);
+
+        let mut single_tag_note = Note::new("Limits");
+        let mut metadata = toml::value::Table::new();
+        metadata.insert("tags".to_string(), toml::Value::from("exam-1"));
+        single_tag_note.set_metadata(metadata);
+
+        let untagged_note = Note::new("Derivatives");
+
+        assert_eq!(note.tags(), vec!["exam-2", "proofs"]);
+        assert_eq!(single_tag_note.tags(), vec!["exam-1"]);
+        assert!(untagged_note.tags().is_empty());
+
+        let notes = vec![note, single_tag_note, untagged_note];
+        let titles = |filter: TagFilter| -> Vec<String> {
+            filter
+                .filter(notes.clone())
+                .iter()
+                .map(Note::title)
+                .collect()
+        };
 
-        slug
+        assert_eq!(titles(TagFilter::default()).len(), 3);
+        assert_eq!(
+            titles(TagFilter::new(vec!["exam-2".into()], vec![])),
+            vec!["Taylor Series"]
+        );
+        assert_eq!(
+            titles(TagFilter::new(
+                vec!["exam-1".into(), "exam-2".into()],
+                vec!["proofs".into()]
+            )),
+            vec!["Limits"]
+        );
+        assert_eq!(
+            titles(TagFilter::new(vec![], vec!["exam-1".into()])),
+            vec!["Taylor Series", "Derivatives"]
+        );
     }
 }
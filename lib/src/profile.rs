@@ -9,23 +9,40 @@ use std::convert::TryFrom;
 use std::fs::{self, DirBuilder, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use minijinja::value::{Kwargs, Rest, Value as JinjaValue};
+use minijinja::State;
 use toml::{self, Value};
 
 use crate::config::ProfileConfig;
 use crate::consts;
 use crate::error::Error;
-use crate::helpers::{self, handlebars as handlebars_helpers};
-use crate::templates::{self, TemplateGetter};
+use crate::helpers::handlebars::{ScriptHelper, ShelfQueryHelper};
+use crate::helpers::shelf::ShelfQueryKind;
+use crate::helpers::{self, handlebars as handlebars_helpers, jinja as jinja_helpers};
+use crate::templates::{self, TemplateGetter, TemplateRegistry};
 use crate::Object;
 
 // profile constants
 pub const PROFILE_METADATA_FILENAME: &str = "profile.toml";
 pub const PROFILE_TEMPLATE_FILES_DIR_NAME: &str = "templates";
+pub const PROFILE_HELPERS_DIR_NAME: &str = "helpers";
 
 pub const TEMPLATE_FILE_EXTENSION: &str = "hbs";
+pub const JINJA_TEMPLATE_FILE_EXTENSION: &str = "j2";
+pub const SCRIPT_HELPER_FILE_EXTENSION: &str = "rhai";
 pub const PROFILE_NOTE_TEMPLATE_NAME: &str = "_default";
 pub const PROFILE_MASTER_NOTE_TEMPLATE_NAME: &str = "master/_default";
+const TYPST_EXTENSION: &str = "typ";
+
+/// Returns the file extension of the resulting document from the template name, if there's any.
+/// For example, a template file at `templates/lecture.md.hbs` has the name `lecture.md` which results in a Markdown document.
+pub fn template_extension<S: AsRef<str>>(name: S) -> Option<String> {
+    Path::new(name.as_ref())
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+}
 
 /// A builder for constructing the profile.
 /// Setting the values does not consume the builder for dynamic setting.
@@ -115,7 +132,7 @@ impl ProfileBuilder {
 pub struct Profile<'a> {
     path: PathBuf,
     config: ProfileConfig,
-    templates: templates::TemplateHandlebarsRegistry<'a>,
+    templates: templates::TemplateRegistries<'a>,
 }
 
 impl<'a> Object for Profile<'a> {
@@ -139,14 +156,15 @@ impl<'a> Profile<'a> {
         Self {
             path: PathBuf::new(),
             config: ProfileConfig::new(),
-            templates: templates::TemplateHandlebarsRegistry::new(),
+            templates: templates::TemplateRegistries::new(),
         }
     }
 
     /// Opens an initiated profile.
     ///
     /// If the profile does not exist in the given path, it will cause an error.
-    /// It will also detect the contents of the files inside of the templates directory to be registered to the Handlebars registry.
+    /// It will also detect the contents of the files inside of the templates directory to be registered to the template registries.
+    /// The Handlebars (`.hbs`) and Jinja (`.j2`) templates are registered in that order so the Jinja templates take precedence over the Handlebars templates with the same name.
     pub fn from<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let path: PathBuf = path.as_ref().to_path_buf();
 
@@ -163,14 +181,15 @@ impl<'a> Profile<'a> {
         profile.init_templates()?;
         // Getting the templates with a specific file extension.
         // This also overrides the default templates if found any.
-        let templates =
-            TemplateGetter::get_templates(profile.templates_path(), TEMPLATE_FILE_EXTENSION)?;
-        match profile.templates.register_vec(&templates) {
-            Err(errors) => return Err(errors),
-            _ => (),
-        };
+        for file_ext in &[TEMPLATE_FILE_EXTENSION, JINJA_TEMPLATE_FILE_EXTENSION] {
+            let templates = TemplateGetter::get_templates(profile.templates_path(), file_ext)?;
+            profile.templates.register_vec(&templates)?;
+        }
 
         profile.config = ProfileConfig::try_from(profile.metadata_path())?;
+        profile
+            .templates
+            .set_escape(profile.config.escape.unwrap_or_default());
 
         Ok(profile)
     }
@@ -183,13 +202,34 @@ impl<'a> Profile<'a> {
         self.path.clone()
     }
 
-    pub fn template_registry(&self) -> &templates::TemplateHandlebarsRegistry {
+    pub fn template_registry(&self) -> &templates::TemplateRegistries<'_> {
         &self.templates
     }
 
+    /// Returns the name of the default template for the documents with the given file extension.
+    /// A template named with the file extension (e.g., `_default.md`) takes precedence over the default template.
+    pub fn default_template_name<S, E>(
+        &self,
+        name: S,
+        extension: E,
+    ) -> String
+    where
+        S: AsRef<str>,
+        E: AsRef<str>,
+    {
+        let name = name.as_ref();
+        let template_name = format!("{}.{}", name, extension.as_ref());
+
+        match self.templates.has_template(&template_name) {
+            true => template_name,
+            false => name.to_string(),
+        }
+    }
+
     /// Initialize the template registry.
     fn init_templates(&mut self) -> Result<(), Error> {
-        let mut registry = templates::TemplateHandlebarsRegistry::new();
+        let mut registries = templates::TemplateRegistries::new();
+        let registry = registries.handlebars_mut();
 
         // registering with the default templates
         registry.register_template_string(PROFILE_NOTE_TEMPLATE_NAME, consts::NOTE_TEMPLATE)?;
@@ -197,6 +237,14 @@ impl<'a> Profile<'a> {
             PROFILE_MASTER_NOTE_TEMPLATE_NAME,
             consts::MASTER_NOTE_TEMPLATE,
         )?;
+        registry.register_template_string(
+            format!("{}.{}", PROFILE_NOTE_TEMPLATE_NAME, TYPST_EXTENSION),
+            consts::TYPST_NOTE_TEMPLATE,
+        )?;
+        registry.register_template_string(
+            format!("{}.{}", PROFILE_MASTER_NOTE_TEMPLATE_NAME, TYPST_EXTENSION),
+            consts::TYPST_MASTER_NOTE_TEMPLATE,
+        )?;
 
         // Registering some helper functions in the Handlebars registry.
         let registry_as_mut = registry.as_mut();
@@ -218,18 +266,131 @@ impl<'a> Profile<'a> {
         registry_as_mut.register_helper("snake-case", Box::new(handlebars_helpers::snake_case));
         registry_as_mut.register_helper("camel-case", Box::new(handlebars_helpers::camel_case));
         registry_as_mut.register_helper("title-case", Box::new(handlebars_helpers::title_case));
+        registry_as_mut.register_helper("slug", Box::new(handlebars_helpers::slug));
+
+        // LaTeX escaping functions.
+        registry_as_mut.register_helper("latex-escape", Box::new(handlebars_helpers::latex_escape));
+        registry_as_mut.register_helper(
+            "latex-escape-url",
+            Box::new(handlebars_helpers::latex_escape_url),
+        );
 
         // Miscellaneous helpers.
         registry_as_mut.register_helper("is-file", Box::new(handlebars_helpers::is_file));
         registry_as_mut.register_helper("is-dir", Box::new(handlebars_helpers::is_dir));
         registry_as_mut.register_helper("reldate", Box::new(handlebars_helpers::reldate));
         registry_as_mut.register_helper("relpath", Box::new(handlebars_helpers::relpath));
+        registry_as_mut.register_helper("note", Box::new(handlebars_helpers::note));
 
-        self.templates = registry;
+        // Shelf query helpers.
+        registry_as_mut.register_helper("notes", Box::new(ShelfQueryHelper(ShelfQueryKind::Notes)));
+        registry_as_mut.register_helper(
+            "note-count",
+            Box::new(ShelfQueryHelper(ShelfQueryKind::NoteCount)),
+        );
+        registry_as_mut.register_helper(
+            "prev-note",
+            Box::new(ShelfQueryHelper(ShelfQueryKind::PreviousNote)),
+        );
+        registry_as_mut.register_helper(
+            "next-note",
+            Box::new(ShelfQueryHelper(ShelfQueryKind::NextNote)),
+        );
+        registry_as_mut.register_helper(
+            "note-pdf",
+            Box::new(ShelfQueryHelper(ShelfQueryKind::NotePdf)),
+        );
+
+        // The same helpers for the Jinja templates with the letter case functions as filters.
+        let environment = registries.jinja_mut().as_mut();
+        environment.add_function("add_float", jinja_helpers::add_float);
+        environment.add_function("add_int", jinja_helpers::add_int);
+        environment.add_function("sub_float", jinja_helpers::sub_float);
+        environment.add_function("sub_int", jinja_helpers::sub_int);
+        environment.add_function("div_float", jinja_helpers::div_float);
+        environment.add_function("div_int", jinja_helpers::div_int);
+        environment.add_function("mul_float", jinja_helpers::mul_float);
+        environment.add_function("mul_int", jinja_helpers::mul_int);
+
+        environment.add_filter("upper_case", jinja_helpers::upper_case);
+        environment.add_filter("lower_case", jinja_helpers::lower_case);
+        environment.add_filter("kebab_case", jinja_helpers::kebab_case);
+        environment.add_filter("snake_case", jinja_helpers::snake_case);
+        environment.add_filter("camel_case", jinja_helpers::camel_case);
+        environment.add_filter("title_case", jinja_helpers::title_case);
+        environment.add_filter("slug", jinja_helpers::slug);
+        environment.add_filter("latex_escape", jinja_helpers::latex_escape);
+        environment.add_filter("latex_escape_url", jinja_helpers::latex_escape_url);
+
+        environment.add_function("is_file", jinja_helpers::is_file);
+        environment.add_function("is_dir", jinja_helpers::is_dir);
+        environment.add_function("reldate", jinja_helpers::reldate);
+        environment.add_function("relpath", jinja_helpers::relpath);
+
+        environment.add_function("notes", jinja_helpers::notes);
+        environment.add_function("note_count", jinja_helpers::note_count);
+        environment.add_function("prev_note", jinja_helpers::prev_note);
+        environment.add_function("next_note", jinja_helpers::next_note);
+        environment.add_function("note_pdf", jinja_helpers::note_pdf);
+
+        self.register_script_helpers(&mut registries)?;
+        self.templates = registries;
 
         Ok(())
     }
 
+    /// Registers the Rhai scripts from the helpers folder as Handlebars helpers named after their file stem.
+    /// They are also registered as Jinja functions with the hyphens replaced with underscores.
+    /// The script helpers take precedence over the built-in helpers with the same name.
+    fn register_script_helpers(
+        &self,
+        registries: &mut templates::TemplateRegistries,
+    ) -> Result<(), Error> {
+        let entries = match fs::read_dir(self.helpers_path()) {
+            Ok(entries) => entries,
+            Err(_e) => return Ok(()),
+        };
+
+        let engine = Arc::new(ScriptHelper::engine());
+        let mut helper_errors = vec![];
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if !path.is_file()
+                || path.extension().and_then(|extension| extension.to_str())
+                    != Some(SCRIPT_HELPER_FILE_EXTENSION)
+            {
+                continue;
+            }
+
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let script = fs::read_to_string(&path).map_err(Error::IoError)?;
+
+            match ScriptHelper::new(&name, engine.clone(), &script) {
+                Ok(helper) => {
+                    registries
+                        .handlebars_mut()
+                        .as_mut()
+                        .register_helper(&name, Box::new(helper.clone()));
+                    registries.jinja_mut().as_mut().add_function(
+                        name.replace('-', "_"),
+                        move |state: &State, params: Rest<JinjaValue>, hash: Kwargs| {
+                            jinja_helpers::script_helper(&helper, state, params, hash)
+                        },
+                    );
+                }
+                Err(error) => helper_errors.push(Error::ScriptHelperError(path, error)),
+            }
+        }
+
+        match helper_errors.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Errors(helper_errors)),
+        }
+    }
+
     /// Returns the metadata file path of the profile.
     pub fn metadata_path(&self) -> PathBuf {
         let mut path = self.path.clone();
@@ -251,6 +412,14 @@ impl<'a> Profile<'a> {
         path
     }
 
+    /// Returns the path of the folder of the helper scripts.
+    pub fn helpers_path(&self) -> PathBuf {
+        let mut path = self.path.clone();
+        path.push(PROFILE_HELPERS_DIR_NAME);
+
+        path
+    }
+
     /// Checks if the templates is in the filesystem.
     pub fn has_templates(&self) -> bool {
         self.templates_path().exists()
@@ -319,7 +488,7 @@ mod tests {
     use crate::note::Note;
     use crate::shelf::{Shelf, ShelfItem};
     use crate::subjects::Subject;
-    use crate::templates::TemplateRegistry;
+    use crate::templates::{TemplateEscape, TemplateRegistry};
     use tempfile;
     use toml;
 
@@ -410,6 +579,228 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn jinja_profile_template_usage() -> Result<(), Error> {
+        let (tmp_dir, mut profile) = tmp_profile()?;
+        profile.export()?;
+
+        let templates_path = profile.templates_path();
+        fs::write(templates_path.join("_default.hbs"), "Handlebars").map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("_default.j2"),
+            "{% if note.tags %}\\usepackage{tags}{% endif %}\n\\title{ {{ note.title | kebab_case }} }\n",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("lecture.md.j2"),
+            "# {{ note.title | upper_case }} ({{ add_int(1, 2, 3) }})",
+        )
+        .map_err(Error::IoError)?;
+
+        let profile = Profile::from(tmp_dir.path())?;
+        let registry = profile.template_registry();
+        let object: toml::Value = toml::from_str("[note]\ntitle = 'Taylor Series'").unwrap();
+
+        // The Jinja template takes precedence over the Handlebars template with the same name.
+        assert_eq!(
+            registry.render("_default", &object)?,
+            "\n\\title{ taylor-series }\n"
+        );
+        assert_eq!(
+            registry.render("lecture.md", &object)?,
+            "# TAYLOR SERIES (6)"
+        );
+        assert!(registry.render("master/_default", &object).is_ok());
+
+        fs::write(templates_path.join("invalid.j2"), "{% if %}").map_err(Error::IoError)?;
+        match Profile::from(tmp_dir.path()) {
+            Err(Error::Errors(errors)) => match errors.first() {
+                Some(Error::JinjaTemplateError(_)) => (),
+                _ => panic!("The invalid template should be a Jinja error."),
+            },
+            _ => panic!("The profile with an invalid template should fail."),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn latex_escaped_profile_template_usage() -> Result<(), Error> {
+        let (tmp_dir, mut profile) = tmp_profile()?;
+        profile.export()?;
+
+        let templates_path = profile.templates_path();
+        fs::write(
+            templates_path.join("report.hbs"),
+            "\\title{ {{~note.title~}} } % {{{note.title}}}\n\\url{ {{~latex-escape-url note.url~}} }",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("report.tex.j2"),
+            "{{ note.title }} {{ note.title | safe }} {{ note.title | latex_escape }}",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(templates_path.join("report.md.j2"), "# {{ note.title }}")
+            .map_err(Error::IoError)?;
+        fs::write(templates_path.join("report.typ.hbs"), "= {{note.title}}")
+            .map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("handout.hbs"),
+            "{{!-- lanoma:\nescape = \"none\"\n--}}\n= {{note.title}}",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("summary.j2"),
+            "{# lanoma:\nescape = \"none\"\n#}\n# {{ note.title }}",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            templates_path.join("table.md.hbs"),
+            "{{!-- lanoma:\nescape = \"latex\"\n--}}\n$$\\text{ {{~note.title~}} }$$",
+        )
+        .map_err(Error::IoError)?;
+
+        let object: toml::Value = toml::from_str(
+            "[note]\ntitle = 'R&D: 100% of $x_1$'\nurl = 'https://example.com/a_b#c'",
+        )
+        .unwrap();
+
+        // The values are not escaped by default.
+        let profile = Profile::from(tmp_dir.path())?;
+        assert_eq!(
+            profile.template_registry().render("report.tex", &object)?,
+            "R&D: 100% of $x_1$ R&D: 100% of $x_1$ R\\&D: 100\\% of \\$x\\_1\\$"
+        );
+
+        let mut config = profile.config().clone();
+        config.escape = Some(TemplateEscape::Latex);
+        fs::write(profile.metadata_path(), toml::to_string(&config).unwrap())
+            .map_err(Error::IoError)?;
+
+        let profile = Profile::from(tmp_dir.path())?;
+        let registry = profile.template_registry();
+        assert_eq!(
+            registry.render("report", &object)?,
+            "\\title{R\\&D: 100\\% of \\$x\\_1\\$} % R&D: 100% of $x_1$\n\\url{https://example.com/a_b\\#c}"
+        );
+        assert_eq!(
+            registry.render("report.tex", &object)?,
+            "R\\&D: 100\\% of \\$x\\_1\\$ R&D: 100% of $x_1$ R\\&D: 100\\% of \\$x\\_1\\$"
+        );
+
+        // The LaTeX escaping is only for the LaTeX documents.
+        assert_eq!(
+            registry.render("report.md", &object)?,
+            "# R&D: 100% of $x_1$"
+        );
+        assert_eq!(
+            registry.render("report.typ", &object)?,
+            "= R&D: 100% of $x_1$"
+        );
+
+        // The escaping from the header of the template takes precedence over the file extension.
+        assert_eq!(registry.render("handout", &object)?, "= R&D: 100% of $x_1$");
+        assert_eq!(registry.render("summary", &object)?, "# R&D: 100% of $x_1$");
+        assert_eq!(
+            registry.render("table.md", &object)?,
+            "$$\\text{R\\&D: 100\\% of \\$x\\_1\\$}$$"
+        );
+        assert_eq!(
+            registry.render_raw_template("{{note.url}}", &object)?,
+            "https://example.com/a_b#c"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn script_helper_usage() -> Result<(), Error> {
+        let (tmp_dir, mut profile) = tmp_profile()?;
+        profile.export()?;
+
+        let helpers_path = profile.helpers_path();
+        fs::create_dir_all(&helpers_path).map_err(Error::IoError)?;
+        fs::write(
+            helpers_path.join("week.rhai"),
+            "let week = params[0] + hash.offset;\n`Week ${week} of ${root.subject.name}`",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            helpers_path.join("upper-case.rhai"),
+            "context.title.to_upper() + \"!\"",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(helpers_path.join("README.md"), "Not a helper.").map_err(Error::IoError)?;
+        fs::write(
+            profile.templates_path().join("lecture.hbs"),
+            "{{week note.week offset=1}}: {{#with note}}{{upper-case}}{{/with}}",
+        )
+        .map_err(Error::IoError)?;
+
+        let profile = Profile::from(tmp_dir.path())?;
+        let object: toml::Value = toml::from_str(
+            "[note]\ntitle = 'Taylor Series'\nweek = 3\n[subject]\nname = 'Calculus'",
+        )
+        .unwrap();
+
+        // The script helpers take precedence over the built-in helpers.
+        assert_eq!(
+            profile.template_registry().render("lecture", &object)?,
+            "Week 4 of Calculus: TAYLOR SERIES!"
+        );
+
+        // The same helpers are available as Jinja functions.
+        fs::write(
+            profile.templates_path().join("lab.j2"),
+            "{{ week(note.week, offset=2) }}: {{ upper_case() }}",
+        )
+        .map_err(Error::IoError)?;
+        let profile = Profile::from(tmp_dir.path())?;
+        let object: toml::Value =
+            toml::from_str("title = 'Pendulum'\n[note]\nweek = 1\n[subject]\nname = 'Physics'")
+                .unwrap();
+        assert_eq!(
+            profile.template_registry().render("lab", &object)?,
+            "Week 3 of Physics: PENDULUM!"
+        );
+
+        // The scripts exceeding the limits fail the rendering instead of hanging.
+        fs::write(helpers_path.join("endless.rhai"), "loop { }").map_err(Error::IoError)?;
+        fs::write(
+            helpers_path.join("recursive.rhai"),
+            "fn f(n) { f(n + 1) }\nf(0)",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            helpers_path.join("huge.rhai"),
+            "let s = \"a\"; loop { s += s; }",
+        )
+        .map_err(Error::IoError)?;
+        let profile = Profile::from(tmp_dir.path())?;
+        let registry = profile.template_registry();
+        for helper in &["endless", "recursive", "huge"] {
+            match registry.render_raw_template(&format!("{{{{{}}}}}", helper), &object) {
+                Err(Error::HandlebarsTemplateRenderError(error)) => {
+                    assert!(error.to_string().contains(helper))
+                }
+                _ => panic!("The script exceeding the limits should be a render error."),
+            }
+        }
+
+        fs::write(helpers_path.join("invalid.rhai"), "let = ;").map_err(Error::IoError)?;
+        match Profile::from(tmp_dir.path()) {
+            Err(Error::Errors(errors)) => match errors.first() {
+                Some(Error::ScriptHelperError(path, _)) => {
+                    assert_eq!(path, &helpers_path.join("invalid.rhai"))
+                }
+                _ => panic!("The invalid script should be a script helper error."),
+            },
+            _ => panic!("The profile with an invalid script should fail."),
+        }
+
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn invalid_profile_export() {
@@ -1,14 +1,22 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::{self, DirBuilder};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
 
+use crate::config::{ShelfConfig, SubjectConfig};
 use crate::error::Error;
 use crate::helpers;
+use crate::index::ShelfIndex;
+use crate::slug::SlugStrategy;
+use crate::subjects::Subject;
 use crate::Object;
 use crate::Result;
 
 use crate::modify_toml_table;
 
+const SHELF_METADATA_FILE: &str = "shelf.toml";
+
 /// A struct holding the common export options.
 #[derive(Debug, Clone)]
 pub struct ExportOptions {
@@ -42,6 +50,12 @@ impl ExportOptions {
 #[derive(Debug, Clone)]
 pub struct Shelf {
     path: PathBuf,
+
+    /// The slug strategy for the file names of the subjects and notes in the shelf.
+    slug: SlugStrategy,
+
+    /// The persistent index shared between the clones of the shelf.
+    index: Option<Arc<Mutex<ShelfIndex>>>,
 }
 
 impl Object for Shelf {
@@ -49,7 +63,8 @@ impl Object for Shelf {
         let mut metadata = toml::Value::from(HashMap::<String, toml::Value>::new());
 
         modify_toml_table! {metadata,
-            ("path", self.path())
+            ("path", self.path()),
+            ("slug", self.slug())
         };
 
         metadata
@@ -64,6 +79,8 @@ impl Shelf {
     {
         Self {
             path: path.as_ref().into(),
+            slug: SlugStrategy::default(),
+            index: None,
         }
     }
 
@@ -72,6 +89,8 @@ impl Shelf {
         let path = path.as_ref();
         let notes_object = Shelf {
             path: path.to_path_buf(),
+            slug: SlugStrategy::default(),
+            index: None,
         };
 
         if !notes_object.is_valid() {
@@ -86,11 +105,95 @@ impl Shelf {
         self.path.clone()
     }
 
+    /// Returns the slug strategy of the shelf.
+    pub fn slug(&self) -> SlugStrategy {
+        self.slug
+    }
+
+    /// Sets the slug strategy of the shelf.
+    /// This should be set before opening the index and making the subjects and notes with the shelf.
+    pub fn set_slug(
+        &mut self,
+        slug: SlugStrategy,
+    ) -> &mut Self {
+        self.slug = slug;
+        self
+    }
+
     /// Checks if the shelf is valid.
     pub fn is_valid(&self) -> bool {
         self.path.is_dir()
     }
 
+    /// Returns the path of the metadata file of the shelf.
+    pub fn metadata_path(&self) -> PathBuf {
+        self.path.join(SHELF_METADATA_FILE)
+    }
+
+    /// Extract the metadata file of the shelf.
+    /// If the shelf has no metadata file, it will return the default configuration.
+    pub fn get_config(&self) -> Result<ShelfConfig> {
+        match self.metadata_path().is_file() {
+            true => ShelfConfig::try_from(self.metadata_path()),
+            false => Ok(ShelfConfig::new()),
+        }
+    }
+
+    /// Opens the persistent index of the shelf to be used in the shelf operations (e.g., searching the notes).
+    /// An invalid index is replaced with an empty one which is filled up again with the operations.
+    pub fn open_index(&mut self) -> Result<&mut Self> {
+        let index = match ShelfIndex::open(&self.path, self.slug) {
+            Ok(index) => index,
+            Err(Error::IoError(error)) => return Err(Error::IoError(error)),
+            Err(_e) => ShelfIndex::new(self.slug),
+        };
+
+        self.index = Some(Arc::new(Mutex::new(index)));
+        Ok(self)
+    }
+
+    /// Returns the index of the shelf if it's opened.
+    pub fn index(&self) -> Option<MutexGuard<'_, ShelfIndex>> {
+        self.index
+            .as_ref()
+            .map(|index| index.lock().unwrap_or_else(|error| error.into_inner()))
+    }
+
+    /// Writes the index into the shelf if it's opened and changed.
+    pub fn save_index(&self) -> Result<()> {
+        match self.index() {
+            Some(mut index) => index.save(&self.path),
+            None => Ok(()),
+        }
+    }
+
+    /// Rebuilds the index from the subjects and notes in the shelf filesystem and writes it into the shelf.
+    /// If the index of the shelf is opened, it is replaced with the rebuilt index.
+    ///
+    /// The notes are searched with the file globs from the metadata of each subject.
+    /// Returns the errors of the notes that are skipped from the index (e.g., an invalid front matter).
+    pub fn reindex(&self) -> Result<Vec<Error>> {
+        let mut shelf = self.clone();
+        if shelf.index.is_none() {
+            shelf.index = Some(Arc::new(Mutex::new(ShelfIndex::new(self.slug))));
+        }
+        if let Some(mut index) = shelf.index() {
+            *index = ShelfIndex::new(self.slug);
+            index.set_changed();
+        }
+
+        let mut skipped: Vec<Error> = vec![];
+        for subject in Subject::new("").get_all_subjects_in_fs(&shelf)? {
+            let subject_config = subject.get_config(&shelf).unwrap_or(SubjectConfig::new());
+            let (_notes, mut skipped_notes) =
+                subject.search_notes_in_fs(&subject_config.file_globs(), &shelf)?;
+            skipped.append(&mut skipped_notes);
+        }
+
+        shelf.save_index()?;
+        Ok(skipped)
+    }
+
     /// Exports the shelf in the filesystem.
     /// If the shelf has a database, it will also export subjects at the filesystem.
     /// However, notes are not exported due to needing a dynamic output.
@@ -231,6 +334,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn subject_name_collision() -> Result<()> {
+        let mut shelf = tmp_shelf()?;
+
+        assert!(shelf.export().is_ok());
+
+        let subject = Subject::new("Calculus I");
+        subject.export(&shelf)?;
+        subject.embed_name(&shelf)?;
+        assert!(subject.check_collision(&shelf).is_ok());
+
+        let colliding_subject = Subject::new("Calculus: I");
+        assert_eq!(colliding_subject.path(), subject.path());
+        assert!(colliding_subject.check_collision(&shelf).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn shelves_with_different_slugs() -> Result<()> {
+        let mut ascii_shelf = tmp_shelf()?;
+        ascii_shelf.export()?;
+        ascii_shelf.set_slug(SlugStrategy::Ascii);
+        let mut unicode_shelf = tmp_shelf()?;
+        unicode_shelf.export()?;
+        unicode_shelf.set_slug(SlugStrategy::Unicode);
+
+        let ascii_subjects = Subject::from_vec_loose(&vec!["Größe"], &ascii_shelf);
+        let unicode_subjects = Subject::from_vec_loose(&vec!["Größe"], &unicode_shelf);
+        assert_eq!(ascii_subjects[0].path(), PathBuf::from("grosse"));
+        assert_eq!(unicode_subjects[0].path(), PathBuf::from("größe"));
+
+        let ascii_notes = Note::from_vec_loose(&vec!["Maße"], &ascii_subjects[0], &ascii_shelf);
+        let unicode_notes =
+            Note::from_vec_loose(&vec!["Maße"], &unicode_subjects[0], &unicode_shelf);
+        assert_eq!(ascii_notes[0].file_name(), "masse.tex");
+        assert_eq!(unicode_notes[0].file_name(), "maße.tex");
+
+        ascii_subjects[0].export(&ascii_shelf)?;
+        ascii_notes[0].export((&ascii_subjects[0], &ascii_shelf))?;
+        let notes = Subject::from_shelf("Größe", &ascii_shelf)?
+            .get_notes_in_fs(&vec![String::from("*.tex")], &ascii_shelf)?;
+        assert_eq!(notes[0].stem(), "masse");
+
+        Ok(())
+    }
+
+    #[test]
+    fn shelf_config_usage() -> Result<()> {
+        let mut shelf = tmp_shelf()?;
+
+        assert!(shelf.export().is_ok());
+
+        let config = shelf.get_config()?;
+        assert!(config.output_dir.is_none());
+        assert!(config.pdf_dir.is_none());
+
+        fs::write(
+            shelf.metadata_path(),
+            "output_dir = 'build'\npdf_dir = 'pdf'",
+        )
+        .map_err(Error::IoError)?;
+        let config = shelf.get_config()?;
+        assert_eq!(config.output_dir, Some(PathBuf::from("build")));
+        assert_eq!(config.pdf_dir, Some(PathBuf::from("pdf")));
+
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn invalid_note_export() {
@@ -0,0 +1,120 @@
+//! The slugs of the notes and subjects in the filesystem.
+//!
+//! The slug strategy is set per shelf (see `Shelf::set_slug`) and carried by the subjects and notes made with the shelf.
+
+use std::str::FromStr;
+
+use deunicode::deunicode;
+use heck::KebabCase;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// The strategies for creating the file names from the titles.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStrategy {
+    /// The kebab case from the earlier versions which splits the words by their letter case (e.g., `LaTeX` into `la-te-x`).
+    #[default]
+    Kebab,
+
+    /// Transliterates the title into ASCII (e.g., `Größe` into `grosse`).
+    Ascii,
+
+    /// Keeps the Unicode letters and digits as they are in lowercase (e.g., `Größe` into `größe`).
+    Unicode,
+}
+
+impl FromStr for SlugStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::Value::String(s.to_string())
+            .try_into()
+            .map_err(Error::TomlValueError)
+    }
+}
+
+impl SlugStrategy {
+    /// Creates the slug of the string with the strategy.
+    pub fn slugify<S: AsRef<str>>(
+        &self,
+        s: S,
+    ) -> String {
+        let s = s.as_ref();
+
+        match self {
+            SlugStrategy::Kebab => s.to_kebab_case(),
+            SlugStrategy::Ascii => hyphenate(&deunicode(s)),
+            SlugStrategy::Unicode => hyphenate(s),
+        }
+    }
+}
+
+/// Lowercases the letters and digits and joins them with a hyphen.
+/// The apostrophes are removed so the possessives stay as one word (e.g., `Taylor's` into `taylors`).
+fn hyphenate(s: &str) -> String {
+    let mut slug = String::new();
+    let mut has_separator = false;
+
+    for c in s.chars().filter(|c| !matches!(c, '\'' | '’')) {
+        if c.is_alphanumeric() {
+            if has_separator && !slug.is_empty() {
+                slug.push('-');
+            }
+
+            slug.extend(c.to_lowercase());
+            has_separator = false;
+        } else {
+            has_separator = true;
+        }
+    }
+
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_strategies() {
+        let title = "L'Hôpital's Rule & Limits";
+        assert_eq!(SlugStrategy::Ascii.slugify(title), "lhopitals-rule-limits");
+        assert_eq!(
+            SlugStrategy::Unicode.slugify(title),
+            "lhôpitals-rule-limits"
+        );
+
+        assert_eq!(
+            SlugStrategy::Ascii.slugify("Größe und Maße"),
+            "grosse-und-masse"
+        );
+        assert_eq!(
+            SlugStrategy::Unicode.slugify("Größe und Maße"),
+            "größe-und-maße"
+        );
+        assert_eq!(
+            SlugStrategy::Unicode.slugify("微分積分 入門"),
+            "微分積分-入門"
+        );
+        assert!(SlugStrategy::Ascii.slugify("微分積分").is_ascii());
+
+        assert_eq!(
+            SlugStrategy::Kebab.slugify("LaTeX Basics"),
+            "la-te-x-basics"
+        );
+        assert_eq!(SlugStrategy::Ascii.slugify("LaTeX Basics"), "latex-basics");
+
+        // Different titles can still have the same slug which should be checked separately.
+        assert_eq!(
+            SlugStrategy::Ascii.slugify("C++ Basics"),
+            SlugStrategy::Ascii.slugify("C Basics")
+        );
+
+        for strategy in &[SlugStrategy::Ascii, SlugStrategy::Unicode] {
+            let slug = strategy.slugify(title);
+            assert_eq!(strategy.slugify(&slug), slug);
+        }
+    }
+}
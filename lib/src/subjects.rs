@@ -1,17 +1,18 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::fs::DirBuilder;
+use std::fs::{self, DirBuilder};
 use std::path::{self, PathBuf};
 
-use heck::KebabCase;
 use serde::{Deserialize, Serialize};
 use toml;
 
 use crate::config;
 use crate::error::Error;
 use crate::helpers;
+use crate::index::FileStamp;
 use crate::note::Note;
 use crate::shelf::{Shelf, ShelfData, ShelfItem};
+use crate::slug::SlugStrategy;
 use crate::{Object, Result};
 
 use crate::{modify_toml_table, upsert_toml_table};
@@ -24,6 +25,10 @@ const SUBJECT_METADATA_FILE: &str = "info.toml";
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Subject {
     name: String,
+
+    /// The slug strategy for the folder names of the subject, taken from the shelf.
+    #[serde(skip)]
+    slug: SlugStrategy,
 }
 
 impl Object for Subject {
@@ -133,6 +138,7 @@ impl Subject {
         };
         Self {
             name: path.to_str().unwrap().to_string(),
+            slug: SlugStrategy::default(),
         }
     }
 
@@ -142,7 +148,8 @@ impl Subject {
         name: &str,
         shelf: &Shelf,
     ) -> Result<Self> {
-        let subject = Subject::new(name);
+        let mut subject = Subject::new(name);
+        subject.set_slug(shelf.slug());
         if !subject.is_item_valid(&shelf) {
             return Err(Error::InvalidSubjectError(subject.path_in_shelf(&shelf)));
         }
@@ -176,7 +183,11 @@ impl Subject {
             .map(
                 |subject| match Subject::from_shelf(subject.as_ref(), &notes) {
                     Ok(v) => v,
-                    Err(_e) => Subject::new(subject.as_ref().to_string()),
+                    Err(_e) => {
+                        let mut subject = Subject::new(subject.as_ref());
+                        subject.set_slug(notes.slug());
+                        subject
+                    }
                 },
             )
             .collect()
@@ -197,6 +208,21 @@ impl Subject {
             .to_string()
     }
 
+    /// Returns the slug strategy of the subject.
+    pub fn slug(&self) -> SlugStrategy {
+        self.slug
+    }
+
+    /// Sets the slug strategy of the subject.
+    /// The subjects made with a shelf (e.g., `Subject::from_shelf`) already have the slug strategy of the shelf.
+    pub fn set_slug(
+        &mut self,
+        slug: SlugStrategy,
+    ) -> &mut Self {
+        self.slug = slug;
+        self
+    }
+
     /// Returns the subject path.
     pub fn path(&self) -> PathBuf {
         PathBuf::from(&self.full_name())
@@ -206,7 +232,7 @@ impl Subject {
                 let s = component.as_os_str().to_str().unwrap();
 
                 match component {
-                    path::Component::Normal(c) => c.to_str().unwrap().to_kebab_case(),
+                    path::Component::Normal(c) => self.slug.slugify(c.to_str().unwrap()),
                     _ => s.to_string(),
                 }
             })
@@ -215,7 +241,17 @@ impl Subject {
 
     /// Returns the last subject component as a subject instance.
     pub fn stem(&self) -> Self {
-        Self::new(self.name())
+        self.with_name(self.name())
+    }
+
+    /// Creates a subject with the given name and the same slug strategy.
+    fn with_name<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> Self {
+        let mut subject = Self::new(name);
+        subject.set_slug(self.slug);
+        subject
     }
 
     /// Returns the associated metadata file path with the given shelf.
@@ -250,7 +286,73 @@ impl Subject {
         &self,
         shelf: &Shelf,
     ) -> Result<config::SubjectConfig> {
-        config::SubjectConfig::try_from(self.metadata_path_in_shelf(&shelf))
+        let metadata_path = self.metadata_path_in_shelf(shelf);
+        let stamp = FileStamp::from_path(&metadata_path);
+        if let (Some(index), Some(stamp)) = (shelf.index(), stamp) {
+            if let Some(config) = index.subject_config(self.path(), stamp) {
+                return Ok(config);
+            }
+        }
+
+        let config = config::SubjectConfig::try_from(metadata_path)?;
+        if let (Some(mut index), Some(stamp)) = (shelf.index(), stamp) {
+            index.insert_subject(self.path(), stamp, &config);
+        }
+
+        Ok(config)
+    }
+
+    /// Checks if the folder of the subject is already used by a different subject in the shelf.
+    ///
+    /// The existing subject is only considered to be different if it has a different name in its metadata file.
+    pub fn check_collision(
+        &self,
+        shelf: &Shelf,
+    ) -> Result<()> {
+        match self.embedded_name(shelf) {
+            Some(existing_name) if existing_name != self.name() => Err(Error::SlugCollisionError(
+                self.path_in_shelf(shelf),
+                existing_name,
+                self.name(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the name of the subject from its metadata file, if there's any.
+    pub fn embedded_name(
+        &self,
+        shelf: &Shelf,
+    ) -> Option<String> {
+        self.get_config(shelf).ok().and_then(|config| {
+            config
+                .extra
+                .get("name")
+                .and_then(toml::Value::as_str)
+                .map(str::to_string)
+        })
+    }
+
+    /// Writes the name of the subject in its metadata file if the name cannot be recovered from the folder name.
+    /// It does nothing if the subject already has a metadata file.
+    pub fn embed_name(
+        &self,
+        shelf: &Shelf,
+    ) -> Result<()> {
+        let folder_name = self
+            .path()
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if self.has_metadata_file(shelf) || folder_name == self.name() {
+            return Ok(());
+        }
+
+        let mut metadata = toml::value::Table::new();
+        metadata.insert("name".to_string(), toml::Value::from(self.name()));
+        let content = toml::to_string(&metadata).map_err(Error::TomlSerializeError)?;
+
+        fs::write(self.metadata_path_in_shelf(shelf), content).map_err(Error::IoError)
     }
 
     /// Returns a vector of the parts of the subject.
@@ -274,46 +376,172 @@ impl Subject {
     pub fn split_subjects(&self) -> Vec<Self> {
         let path = PathBuf::from(&self.name);
         path.ancestors()
-            .map(|ancestor| Self::new(ancestor.to_string_lossy()))
+            .map(|ancestor| self.with_name(ancestor.to_string_lossy()))
             .filter(|subject| !subject.full_name().is_empty())
             .collect()
     }
 
+    /// Get the subjects directly under the subject in the shelf filesystem.
+    /// Hidden folders (i.e., starting with a dot) are not considered as subjects.
+    /// The resulting subjects are sorted by their name.
+    pub fn get_subjects_in_fs(
+        &self,
+        shelf: &Shelf,
+    ) -> Result<Vec<Self>> {
+        let mut subjects: Vec<Self> = vec![];
+
+        for entry in fs::read_dir(self.path_in_shelf(shelf)).map_err(Error::IoError)? {
+            let entry = entry.map_err(Error::IoError)?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            if !entry.path().is_dir() || file_name.starts_with('.') {
+                continue;
+            }
+
+            let mut subject = Self::new(self.path().join(file_name).to_string_lossy());
+            subject.set_slug(shelf.slug());
+
+            // The name from the metadata file is only used if it still leads to the same folder.
+            if let Some(name) = subject.embedded_name(shelf) {
+                let named_subject = subject.with_name(self.path().join(name).to_string_lossy());
+                if named_subject.path() == subject.path() {
+                    subject = named_subject;
+                }
+            }
+            subjects.push(subject);
+        }
+
+        subjects.sort_by_key(|subject| subject.name());
+        Ok(subjects)
+    }
+
+    /// Get all of the subjects under the subject in the shelf filesystem.
+    /// The subjects are ordered with the child subjects right after their parent.
+    pub fn get_all_subjects_in_fs(
+        &self,
+        shelf: &Shelf,
+    ) -> Result<Vec<Self>> {
+        let mut subjects: Vec<Self> = vec![];
+
+        for child_subject in self.get_subjects_in_fs(shelf)? {
+            let mut grandchild_subjects = child_subject.get_all_subjects_in_fs(shelf)?;
+
+            subjects.push(child_subject);
+            subjects.append(&mut grandchild_subjects);
+        }
+
+        Ok(subjects)
+    }
+
     /// Get the notes in the shelf filesystem.
+    /// The notes with an invalid front matter are skipped (see `search_notes_in_fs` for the reasons).
+    ///
+    /// If the shelf has an opened index, the unchanged notes are taken from the index instead of reading the files.
     pub fn get_notes_in_fs(
         &self,
         file_globs: &Vec<String>,
         shelf: &Shelf,
     ) -> Result<Vec<Note>> {
+        self.search_notes_in_fs(file_globs, shelf)
+            .map(|(notes, _skipped)| notes)
+    }
+
+    /// Get the notes in the shelf filesystem along with the errors of the skipped notes.
+    ///
+    /// An invalid front matter does not hide the rest of the notes in the subject.
+    /// Instead, the note is skipped and its `FrontMatterError` is returned next to the notes.
+    ///
+    /// With the index of the shelf, the subject folder is only searched again if it has been changed.
+    pub fn search_notes_in_fs(
+        &self,
+        file_globs: &Vec<String>,
+        shelf: &Shelf,
+    ) -> Result<(Vec<Note>, Vec<Error>)> {
         let mut notes: Vec<Note> = vec![];
+        let mut skipped: Vec<Error> = vec![];
 
         let subject_path = self.path_in_shelf(&shelf);
 
-        let tex_files = globwalk::GlobWalkerBuilder::from_patterns(subject_path, &file_globs)
-            .build()
-            .map_err(Error::GlobParsingError)?;
+        // Only the files directly under the subject can be notes (see `Note::from_file`).
+        // This lets the index list the files while the stamp of the subject folder is unchanged.
+        let folder_stamp = FileStamp::from_path(&subject_path);
+        let indexed_files = match (shelf.index(), folder_stamp) {
+            (Some(index), Some(stamp)) => index
+                .listing(self.path(), stamp, file_globs)
+                .map(|files| files.iter().map(|file| subject_path.join(file)).collect()),
+            _ => None,
+        };
+        let is_listed = indexed_files.is_some();
+        let files: Vec<PathBuf> = match indexed_files {
+            Some(files) => files,
+            None => globwalk::GlobWalkerBuilder::from_patterns(&subject_path, &file_globs)
+                .build()
+                .map_err(Error::GlobParsingError)?
+                .filter_map(|file| file.ok())
+                .map(|file| file.into_path())
+                .filter(|file| file.parent() == Some(subject_path.as_path()))
+                .collect(),
+        };
 
-        for file in tex_files {
-            if let Ok(file) = file {
-                let note_path = file.path();
+        for note_path in files.iter() {
+            let file_name = match note_path.file_name() {
+                Some(file_name) => file_name.to_string_lossy(),
+                None => continue,
+            };
+            let note_path_in_index = self.path().join(file_name.as_ref());
+            let stamp = FileStamp::from_path(note_path);
+
+            if let (Some(index), Some(stamp)) = (shelf.index(), stamp) {
+                if let Some(note) = index.note(&note_path_in_index, stamp) {
+                    notes.push(note.clone());
+                    continue;
+                }
+            }
 
-                let file_stem = note_path.file_stem().unwrap().to_string_lossy();
+            // All of the notes may not have a kebab-case as their file name so we have to check it if it's a valid note.
+            let note = match Note::from_file(&file_name, self, shelf) {
+                Ok(note) => note,
+                Err(error @ Error::FrontMatterError(_, _)) => {
+                    skipped.push(error);
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
 
-                // All of the notes may not have a kebab-case as their file name so we have to check it if it's a valid note.
-                match Note::from(file_stem, &self, &shelf) {
-                    Some(v) => notes.push(v),
-                    None => continue,
+            if let Some(note) = note {
+                if let (Some(mut index), Some(stamp)) = (shelf.index(), stamp) {
+                    index.insert_note(&note_path_in_index, stamp, &note);
                 }
+
+                notes.push(note);
             }
         }
 
-        Ok(notes)
+        if is_listed {
+            return Ok((notes, skipped));
+        }
+
+        if let (Some(mut index), Some(stamp)) = (shelf.index(), folder_stamp) {
+            let file_names = files
+                .iter()
+                .filter_map(|file| file.file_name())
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .collect();
+            index.insert_listing(self.path(), stamp, file_globs, file_names);
+        }
+
+        if let Some(mut index) = shelf.index() {
+            index.prune_notes(self.path(), shelf.path());
+        }
+
+        Ok((notes, skipped))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile;
 
     #[test]
     fn basic_subject() {
@@ -443,6 +671,37 @@ mod tests {
         assert!(subject_part.next().is_none());
     }
 
+    #[test]
+    fn notes_with_invalid_front_matter() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new().map_err(Error::IoError)?;
+        let shelf = Shelf::from(tmp_dir.path())?;
+        let subject = Subject::new("Calculus");
+        subject.export(&shelf)?;
+
+        let subject_path = subject.path_in_shelf(&shelf);
+        fs::write(
+            subject_path.join("limits.tex"),
+            "%% lanoma: tags = [\"exam\"]\n",
+        )
+        .map_err(Error::IoError)?;
+        fs::write(
+            subject_path.join("derivatives.tex"),
+            "%% lanoma: tags = [\n",
+        )
+        .map_err(Error::IoError)?;
+
+        let (notes, skipped) = subject.search_notes_in_fs(&vec![String::from("*.tex")], &shelf)?;
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title(), "limits");
+        assert_eq!(skipped.len(), 1);
+        match &skipped[0] {
+            Error::FrontMatterError(path, _) => assert!(path.ends_with("derivatives.tex")),
+            error => panic!("unexpected error: {:?}", error),
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn basic_note() {
         let subject = Subject::new("Calculus");
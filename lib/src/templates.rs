@@ -1,19 +1,41 @@
-//! An adapter for a template engine.
-//! This is implemented in case Lanoma decides to support multiple template engine.
+//! An adapter for the template engines.
+//! Lanoma supports Handlebars and Jinja templates which are selected by the file extension of the template.
 //!
-//! (On the other hand, this may be just a case of overengineering.)
+//! Both registries are combined in one registry where the templates share the same names.
 
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::ops::Deref;
 use std::path::Path;
 
 use globwalk;
 use handlebars;
-use serde;
+use minijinja;
+use serde::{self, Deserialize, Serialize};
+use toml;
 
 use crate::error::Error;
 use crate::helpers;
 
+thread_local! {
+    /// The escaping of the template currently rendered by the registries.
+    /// The inline templates that are not documents (e.g., the compilation commands) are never escaped.
+    static ESCAPE: Cell<TemplateEscape> = const { Cell::new(TemplateEscape::None) };
+}
+
+/// Runs the closure with the escaping set for the templates rendered in it.
+fn with_escape<T, F: FnOnce() -> T>(
+    escape: TemplateEscape,
+    f: F,
+) -> T {
+    let previous = ESCAPE.with(|current| current.replace(escape));
+    let result = f();
+    ESCAPE.with(|current| current.set(previous));
+
+    result
+}
+
 /// A trait for the template registry.
 /// It handles all of the template operations such as checking if the there is already a template
 /// with the specified name, rendering them, and including templates in the template list.
@@ -174,11 +196,465 @@ impl<'a> TemplateHandlebarsRegistry<'a> {
     }
 }
 
+/// The template registry implemented with the `minijinja` crate.
+#[derive(Debug)]
+pub struct TemplateJinjaRegistry(minijinja::Environment<'static>);
+
+impl TemplateRegistry for TemplateJinjaRegistry {
+    /// Registers a template in the registry.
+    /// If there is a template with the same name, it will be overwritten.
+    fn register(
+        &mut self,
+        template: &Template,
+    ) -> Result<(), Error> {
+        self.0
+            .add_template_owned(template.name.clone(), template.s.clone())
+            .map_err(Error::JinjaTemplateError)
+    }
+
+    fn unregister<S>(
+        &mut self,
+        template_name: S,
+    ) -> Result<(), Error>
+    where
+        S: AsRef<str>,
+    {
+        self.0.remove_template(template_name.as_ref());
+
+        Ok(())
+    }
+
+    fn has_template<S>(
+        &self,
+        name: S,
+    ) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.0.get_template(name.as_ref()).is_ok()
+    }
+
+    fn render<S, V>(
+        &self,
+        template_name: S,
+        value: V,
+    ) -> Result<String, Error>
+    where
+        S: AsRef<str>,
+        V: serde::Serialize,
+    {
+        self.0
+            .get_template(template_name.as_ref())
+            .and_then(|template| template.render(&value))
+            .map_err(Error::JinjaRenderError)
+    }
+}
+
+impl Deref for TemplateJinjaRegistry {
+    type Target = minijinja::Environment<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsMut<minijinja::Environment<'static>> for TemplateJinjaRegistry {
+    fn as_mut(&mut self) -> &mut minijinja::Environment<'static> {
+        &mut self.0
+    }
+}
+
+impl Default for TemplateJinjaRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateJinjaRegistry {
+    /// Creates a new instance of the registry.
+    /// Similar to the Handlebars registry, the values are not escaped and the resulting documents keep their trailing newline.
+    pub fn new() -> Self {
+        let mut environment = minijinja::Environment::new();
+        environment.set_auto_escape_callback(|_name| minijinja::AutoEscape::None);
+        environment.set_keep_trailing_newline(true);
+
+        Self(environment)
+    }
+}
+
+/// The registry of all of the supported template engines.
+///
+/// The templates from both engines share the same names.
+/// Registering a template replaces the template with the same name from the other engine.
+#[derive(Debug)]
+pub struct TemplateRegistries<'a> {
+    handlebars: TemplateHandlebarsRegistry<'a>,
+    jinja: TemplateJinjaRegistry,
+    headers: HashMap<String, TemplateHeader>,
+    escape: TemplateEscape,
+}
+
+impl<'a> TemplateRegistry for TemplateRegistries<'a> {
+    /// Registers a template in the registry of its template engine.
+    fn register(
+        &mut self,
+        template: &Template,
+    ) -> Result<(), Error> {
+        self.headers
+            .insert(template.name.clone(), template.header.clone());
+
+        match template.engine {
+            TemplateEngine::Handlebars => {
+                self.handlebars.register(template)?;
+                self.jinja.unregister(&template.name)
+            }
+            TemplateEngine::Jinja => {
+                self.jinja.register(template)?;
+                self.handlebars.unregister(&template.name)
+            }
+        }
+    }
+
+    fn unregister<S>(
+        &mut self,
+        template_name: S,
+    ) -> Result<(), Error>
+    where
+        S: AsRef<str>,
+    {
+        let template_name = template_name.as_ref();
+
+        self.headers.remove(template_name);
+        self.handlebars.unregister(template_name)?;
+        self.jinja.unregister(template_name)
+    }
+
+    fn has_template<S>(
+        &self,
+        name: S,
+    ) -> bool
+    where
+        S: AsRef<str>,
+    {
+        let name = name.as_ref();
+
+        self.handlebars.has_template(name) || self.jinja.has_template(name)
+    }
+
+    /// Renders the template with the engine it's registered in.
+    /// If the template is not found, it is left for the Handlebars registry to report the error.
+    ///
+    /// The escaping from the header of the template takes precedence over the escaping of the registry.
+    fn render<S, V>(
+        &self,
+        template_name: S,
+        value: V,
+    ) -> Result<String, Error>
+    where
+        S: AsRef<str>,
+        V: serde::Serialize,
+    {
+        let template_name = template_name.as_ref();
+
+        with_escape(self.escape_for(template_name), || {
+            match self.jinja.has_template(template_name) {
+                true => self.jinja.render(template_name, value),
+                false => self.handlebars.render(template_name, value),
+            }
+        })
+    }
+}
+
+impl<'a> Default for TemplateRegistries<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> TemplateRegistries<'a> {
+    /// Creates a new instance of the registries.
+    /// The values are escaped with the escaping of the template being rendered which is none by default.
+    pub fn new() -> Self {
+        let mut handlebars = TemplateHandlebarsRegistry::new();
+        handlebars
+            .as_mut()
+            .register_escape_fn(|s| ESCAPE.with(Cell::get).escape(s));
+
+        let mut jinja = TemplateJinjaRegistry::new();
+        jinja
+            .as_mut()
+            .set_formatter(|out, state, value| match value.as_str() {
+                Some(s) if !value.is_safe() => {
+                    out.write_str(&ESCAPE.with(Cell::get).escape(s))?;
+                    Ok(())
+                }
+                _ => minijinja::escape_formatter(out, state, value),
+            });
+
+        Self {
+            handlebars,
+            jinja,
+            headers: HashMap::new(),
+            escape: TemplateEscape::default(),
+        }
+    }
+
+    /// Returns the header of the template.
+    /// The templates without a header (e.g., the default templates) have an empty header.
+    pub fn header<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> TemplateHeader {
+        self.headers.get(name.as_ref()).cloned().unwrap_or_default()
+    }
+
+    /// Returns the escaping of the values in the template.
+    /// The escaping from the header of the template is used if there's any.
+    /// Otherwise, the escaping of the registry is only applied to the templates of the documents it's made for (see `TemplateEscape::for_template`).
+    fn escape_for<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> TemplateEscape {
+        let name = name.as_ref();
+
+        self.headers
+            .get(name)
+            .and_then(|header| header.escape)
+            .unwrap_or_else(|| self.escape.for_template(name))
+    }
+
+    /// Sets the escaping of the values in the templates of both engines.
+    ///
+    /// The escaping applies to `{{...}}` in Handlebars templates while `{{{...}}}` stays raw.
+    /// For Jinja templates, it applies to every string value not marked with the `safe` filter.
+    /// Either way, it only applies to the templates of the documents it's made for (e.g., not the Markdown templates for the LaTeX escaping) unless their header says otherwise.
+    pub fn set_escape(
+        &mut self,
+        escape: TemplateEscape,
+    ) {
+        self.escape = escape;
+    }
+
+    /// Renders the inline Handlebars template without escaping any of the values.
+    /// This is used for the templates that are not documents such as the compilation commands and file paths.
+    pub fn render_raw_template<V: serde::Serialize>(
+        &self,
+        template: &str,
+        value: &V,
+    ) -> Result<String, Error> {
+        with_escape(TemplateEscape::None, || {
+            self.handlebars.render_template(template, value)
+        })
+        .map_err(Error::HandlebarsTemplateRenderError)
+    }
+
+    /// Returns the Handlebars registry.
+    pub fn handlebars(&self) -> &TemplateHandlebarsRegistry<'a> {
+        &self.handlebars
+    }
+
+    /// Returns the Handlebars registry as a mutable reference.
+    pub fn handlebars_mut(&mut self) -> &mut TemplateHandlebarsRegistry<'a> {
+        &mut self.handlebars
+    }
+
+    /// Returns the Jinja registry.
+    pub fn jinja(&self) -> &TemplateJinjaRegistry {
+        &self.jinja
+    }
+
+    /// Returns the Jinja registry as a mutable reference.
+    pub fn jinja_mut(&mut self) -> &mut TemplateJinjaRegistry {
+        &mut self.jinja
+    }
+
+    /// Register a vector of templates in the registry of their template engine.
+    pub fn register_vec<'b>(
+        &mut self,
+        templates: &'b [Template],
+    ) -> Result<Vec<&'b Template>, Error> {
+        let mut registered_templates = vec![];
+        let mut template_errors = vec![];
+        for template in templates.iter() {
+            match self.register(template) {
+                Ok(_v) => registered_templates.push(template),
+                Err(e) => template_errors.push(e),
+            }
+        }
+
+        match template_errors.is_empty() {
+            true => Ok(registered_templates),
+            false => Err(Error::Errors(template_errors)),
+        }
+    }
+}
+
+/// The supported template engines.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TemplateEngine {
+    #[default]
+    Handlebars,
+    Jinja,
+}
+
+impl TemplateEngine {
+    /// Returns the opening and closing lines of the comment containing the template header.
+    fn header_delimiters(&self) -> (&'static str, &'static str) {
+        match self {
+            TemplateEngine::Handlebars => ("{{!-- lanoma:", "--}}"),
+            TemplateEngine::Jinja => ("{# lanoma:", "#}"),
+        }
+    }
+
+    /// Returns the template engine from the file extension of the template file.
+    pub fn from_extension<S: AsRef<str>>(extension: S) -> Option<Self> {
+        match extension.as_ref() {
+            "hbs" => Some(TemplateEngine::Handlebars),
+            "j2" => Some(TemplateEngine::Jinja),
+            _ => None,
+        }
+    }
+}
+
+/// The escaping applied to the values in the templates.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateEscape {
+    /// The values are rendered as they are.
+    #[default]
+    None,
+
+    /// The special characters of LaTeX are escaped.
+    Latex,
+}
+
+impl TemplateEscape {
+    /// Returns the escaping for the template with the given name.
+    ///
+    /// The file extension of the template name is the file extension of the resulting document (e.g., `lecture.md` for a Markdown document).
+    /// The LaTeX escaping only applies to the templates of LaTeX documents which are the ones with the `.tex` extension or without any (e.g., `_default`).
+    pub fn for_template<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> Self {
+        let extension = Path::new(name.as_ref())
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        match (self, extension.as_deref()) {
+            (TemplateEscape::Latex, None) | (TemplateEscape::Latex, Some("tex")) => *self,
+            _ => TemplateEscape::None,
+        }
+    }
+
+    /// Escapes the string.
+    pub fn escape(
+        &self,
+        s: &str,
+    ) -> String {
+        match self {
+            TemplateEscape::None => s.to_string(),
+            TemplateEscape::Latex => helpers::latex::escape(s),
+        }
+    }
+}
+
+/// A variable declared in the header of a template.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TemplateVariable {
+    /// The description of the variable shown when prompting for it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The default value of the variable.
+    /// A variable without a default value is required.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<toml::Value>,
+}
+
+/// The header of a template containing its metadata.
+///
+/// The header is a TOML table in a comment at the start of the template file.
+/// The first line of the comment is `{{!-- lanoma:` for Handlebars and `{# lanoma:` for Jinja.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TemplateHeader {
+    /// The variables to be given when rendering the template.
+    #[serde(default)]
+    pub vars: BTreeMap<String, TemplateVariable>,
+
+    /// The escaping of the values in the template.
+    /// Without it, the escaping of the registry is applied depending on the file extension of the template name (see `TemplateEscape::for_template`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape: Option<TemplateEscape>,
+}
+
+impl TemplateHeader {
+    /// Splits the header from the rest of the template.
+    /// A template without a header has an empty header.
+    pub fn parse<S: AsRef<str>>(
+        s: S,
+        engine: TemplateEngine,
+    ) -> Result<(Self, String), Error> {
+        let s = s.as_ref();
+        let (opening, closing) = engine.header_delimiters();
+
+        let mut lines = s.split_inclusive('\n');
+        match lines.next() {
+            Some(line) if line.trim_end() == opening => (),
+            _ => return Ok((Self::default(), s.to_string())),
+        };
+
+        let mut header = String::new();
+        for line in &mut lines {
+            if line.trim_end() == closing {
+                let header = toml::from_str(&header).map_err(Error::TomlValueError)?;
+                return Ok((header, lines.collect()));
+            }
+
+            header.push_str(line);
+        }
+
+        Err(Error::MissingDataError(format!(
+            "The closing '{}' of the template header",
+            closing
+        )))
+    }
+
+    /// Returns the variables with the given values and the defaults from the header.
+    /// The given values take precedence over the defaults.
+    ///
+    /// The required variables without a given value are returned separately.
+    pub fn resolve(
+        &self,
+        values: &toml::value::Table,
+    ) -> (toml::value::Table, Vec<(String, TemplateVariable)>) {
+        let mut vars = values.clone();
+        let mut missing_vars = vec![];
+
+        for (name, var) in self.vars.iter() {
+            if vars.contains_key(name) {
+                continue;
+            }
+
+            match &var.default {
+                Some(default) => {
+                    vars.insert(name.clone(), default.clone());
+                }
+                None => missing_vars.push((name.clone(), var.clone())),
+            }
+        }
+
+        (vars, missing_vars)
+    }
+}
+
 /// A generic struct for templates to be used in a template engine.
 #[derive(Debug)]
 pub struct Template {
     name: String,
     s: String,
+    engine: TemplateEngine,
+    header: TemplateHeader,
 }
 
 impl Template {
@@ -186,6 +662,8 @@ impl Template {
         Self {
             name: String::new(),
             s: String::new(),
+            engine: TemplateEngine::default(),
+            header: TemplateHeader::default(),
         }
     }
 
@@ -200,10 +678,17 @@ impl Template {
         let path = path.as_ref();
         let name = name.as_ref();
         let s = fs::read_to_string(&path).map_err(Error::IoError)?;
+        let engine = path
+            .extension()
+            .and_then(|extension| TemplateEngine::from_extension(extension.to_string_lossy()))
+            .unwrap_or_default();
+        let (header, s) = TemplateHeader::parse(s, engine)?;
 
         Ok(Self {
             name: name.to_string(),
             s,
+            engine,
+            header,
         })
     }
 }
@@ -238,7 +723,8 @@ impl TemplateGetter {
                     &path_as_str[..path_as_str.len() - file_ext.len() - 1];
                 match Template::from_path(file.path(), relpath_from_path_without_file_ext) {
                     Ok(v) => templates.push(v),
-                    Err(_e) => continue,
+                    Err(Error::IoError(_e)) => continue,
+                    Err(e) => return Err(e),
                 }
             }
         }
@@ -272,4 +758,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn template_header() -> Result<(), Error> {
+        let (header, s) = TemplateHeader::parse(
+            "{{!-- lanoma:\n[vars.lecturer]\ndescription = 'The lecturer'\n[vars.week]\ndefault = 1\n--}}\n\\title{}\n",
+            TemplateEngine::Handlebars,
+        )?;
+        assert_eq!(s, "\\title{}\n");
+        assert_eq!(header.vars.len(), 2);
+
+        let mut values = toml::value::Table::new();
+        let (vars, missing_vars) = header.resolve(&values);
+        assert_eq!(vars.get("week"), Some(&toml::Value::from(1)));
+        assert_eq!(missing_vars.len(), 1);
+        assert_eq!(missing_vars[0].0, "lecturer");
+
+        values.insert("lecturer".to_string(), toml::Value::from("Dr. Lee"));
+        values.insert("week".to_string(), toml::Value::from(3));
+        let (vars, missing_vars) = header.resolve(&values);
+        assert_eq!(vars.get("week"), Some(&toml::Value::from(3)));
+        assert!(missing_vars.is_empty());
+
+        // The header is only in the comment syntax of the template engine.
+        let (header, s) =
+            TemplateHeader::parse("{# lanoma:\n[vars.week]\n#}\n", TemplateEngine::Handlebars)?;
+        assert!(header.vars.is_empty());
+        assert_eq!(s, "{# lanoma:\n[vars.week]\n#}\n");
+        assert!(TemplateHeader::parse("{# lanoma:\n[vars.week]\n", TemplateEngine::Jinja).is_err());
+
+        Ok(())
+    }
 }
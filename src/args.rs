@@ -1,6 +1,15 @@
 use std::path::PathBuf;
+
+use lanoma_lib::config::Engine;
+use lanoma_lib::note::TagFilter;
 use structopt::StructOpt;
 
+use crate::helpers;
+
+const ENGINES: &[&str] = &[
+    "latexmk", "pdflatex", "xelatex", "lualatex", "tectonic", "typst",
+];
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Lanoma", about = "Manage your LaTeX study notes.")]
 pub struct Lanoma {
@@ -48,6 +57,76 @@ pub enum Input {
     },
 }
 
+#[derive(Debug, StructOpt)]
+pub enum CompileInput {
+    Subjects {
+        #[structopt(help = "Compile the notes of a list of subjects.", min_values = 1)]
+        subjects: Vec<String>,
+    },
+
+    Notes {
+        #[structopt(help = "The subject of the notes.")]
+        subject: String,
+
+        #[structopt(
+            min_values = 1,
+            multiple = true,
+            required = true,
+            last = true,
+            help = "A list of notes for a particular subject. Requires the subject as the first item in the list."
+        )]
+        notes: Vec<String>,
+    },
+
+    #[structopt(about = "Compile the existing master notes without regenerating them.")]
+    Master {
+        #[structopt(
+            help = "Compile the master notes of a list of subjects.",
+            min_values = 1
+        )]
+        subjects: Vec<String>,
+
+        #[structopt(
+            short,
+            long,
+            help = "The name of the master note from the 'masters' table of the subject metadata."
+        )]
+        name: Option<String>,
+    },
+}
+
+/// The tag selectors shared by the subcommands searching for notes.
+#[derive(Debug, StructOpt)]
+pub struct TagArgs {
+    #[structopt(
+        long = "tag",
+        value_name = "tag",
+        number_of_values = 1,
+        help = "Only include the notes with the tag. If given multiple times, the notes with any of the tags are included."
+    )]
+    pub tags: Vec<String>,
+
+    #[structopt(
+        long = "exclude-tag",
+        value_name = "tag",
+        number_of_values = 1,
+        help = "Exclude the notes with the tag. Can be given multiple times."
+    )]
+    pub exclude_tags: Vec<String>,
+}
+
+impl TagArgs {
+    /// Returns the tag filter if any of the tags are given.
+    pub fn tag_filter(&self) -> Option<TagFilter> {
+        let tag_filter = TagFilter::new(self.tags.clone(), self.exclude_tags.clone());
+
+        match tag_filter.is_empty() {
+            true => None,
+            false => Some(tag_filter),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum Command {
     #[structopt(about = "Initialize a profile.")]
@@ -70,6 +149,15 @@ pub enum Command {
             help = "The name of the template to be used for creating the notes."
         )]
         template: Option<String>,
+
+        #[structopt(
+            long = "var",
+            value_name = "key=value",
+            number_of_values = 1,
+            parse(try_from_str = parse_var),
+            help = "Sets a variable for the template available under 'vars'. The value is parsed as a TOML value if it's valid."
+        )]
+        vars: Vec<(String, toml::Value)>,
     },
 
     #[structopt(about = "Remove multiple subjects and notes in the database.")]
@@ -80,6 +168,11 @@ pub enum Command {
 
     #[structopt(about = "Lists the subjects and its notes from the database.")]
     List {
+        #[structopt(
+            help = "The subjects to be listed. By default, it lists all of the subjects in the shelf."
+        )]
+        subjects: Vec<String>,
+
         #[structopt(short, long, possible_values = &["date", "name"], help = "Sort the entries.")]
         sort: Option<String>,
 
@@ -92,12 +185,18 @@ pub enum Command {
 
         #[structopt(short, long, help = "Reverse the list.")]
         reverse: bool,
+
+        #[structopt(flatten)]
+        tags: TagArgs,
     },
 
+    #[structopt(about = "Rebuild the index of the shelf from the filesystem.")]
+    Reindex,
+
     #[structopt(about = "Compile the notes.")]
     Compile {
         #[structopt(subcommand)]
-        kind: Input,
+        kind: CompileInput,
 
         #[structopt(
             short,
@@ -110,8 +209,25 @@ pub enum Command {
         #[structopt(short, long, help = "Specifies what files to be compiled.")]
         files: Option<Vec<String>>,
 
+        #[structopt(flatten)]
+        tags: TagArgs,
+
         #[structopt(short, long, help = "Overrides the default compilation command.")]
         command: Option<String>,
+
+        #[structopt(
+            short,
+            long,
+            possible_values = ENGINES,
+            help = "Overrides the compiler preset. The command option has the higher precedence."
+        )]
+        engine: Option<Engine>,
+
+        #[structopt(
+            long,
+            help = "Stops starting new compilation jobs after the first failure."
+        )]
+        fail_fast: bool,
     },
 
     #[structopt(about = "A subcommand dedicated to interact with master notes.")]
@@ -122,6 +238,27 @@ pub enum Command {
         #[structopt(short, long, help = "Skip the compilation step.")]
         skip_compilation: bool,
 
+        #[structopt(
+            long,
+            default_value = "4",
+            help = "Creates a specified number of threads compiling the master notes in parallel."
+        )]
+        thread_count: i64,
+
+        #[structopt(
+            short,
+            long,
+            help = "Include the notes of the child subjects as parts of the master note."
+        )]
+        recursive: bool,
+
+        #[structopt(
+            short,
+            long,
+            help = "The name of the master note from the 'masters' table of the subject metadata."
+        )]
+        name: Option<String>,
+
         #[structopt(
             short,
             long,
@@ -129,6 +266,9 @@ pub enum Command {
         )]
         files: Option<Vec<String>>,
 
+        #[structopt(flatten)]
+        tags: TagArgs,
+
         #[structopt(
             short,
             long,
@@ -142,5 +282,29 @@ pub enum Command {
             help = "The command to be used to compile the master note."
         )]
         command: Option<String>,
+
+        #[structopt(
+            short,
+            long,
+            possible_values = ENGINES,
+            help = "The compiler preset to be used to compile the master note. The command option has the higher precedence."
+        )]
+        engine: Option<Engine>,
+
+        #[structopt(
+            long,
+            help = "Stops starting new compilation jobs after the first failure."
+        )]
+        fail_fast: bool,
     },
 }
+
+/// Parses the template variable from the command line in the form of `key=value`.
+fn parse_var(s: &str) -> Result<(String, toml::Value), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), helpers::parse_var_value(value)))
+        }
+        _ => Err(format!("'{}' is not in the form of 'key=value'.", s)),
+    }
+}
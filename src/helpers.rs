@@ -1,47 +1,27 @@
 use std::collections::HashMap;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{self, Path, PathBuf};
-use std::process;
 
 use toml;
 
-use lanoma_lib::config::SubjectConfig;
+use lanoma_lib::compile::CompilationEnvironment;
+use lanoma_lib::config::{Compiler, Engine, ShelfConfig, SubjectConfig};
 use lanoma_lib::error::Error;
-use lanoma_lib::masternote::MasterNote;
 use lanoma_lib::modify_toml_table;
 use lanoma_lib::note::Note;
 use lanoma_lib::profile::Profile;
 use lanoma_lib::shelf::{Shelf, ShelfData};
 use lanoma_lib::subjects::Subject;
+use lanoma_lib::templates::TemplateVariable;
 use lanoma_lib::Object;
 
-pub fn master_note_full_object(
-    profile: &Profile,
-    shelf: &Shelf,
-    master_note: &MasterNote,
-) -> toml::Value {
-    let subject_as_toml = ShelfData::data(master_note.subject(), &shelf);
-    let master_note_as_toml = ShelfData::data(master_note, &shelf);
-    let profile_config = Object::data(profile);
-    let shelf_data = Object::data(shelf);
-
-    let mut metadata = toml::Value::from(HashMap::<String, toml::Value>::new());
-    modify_toml_table! {metadata,
-        ("profile", profile_config),
-        ("subject", subject_as_toml),
-        ("master", master_note_as_toml),
-        ("shelf", shelf_data)
-    }
-
-    metadata
-}
-
 pub fn note_full_object(
     profile: &Profile,
     shelf: &Shelf,
     note: &Note,
     subject: &Subject,
+    vars: &toml::value::Table,
 ) -> toml::Value {
     let subject_toml = ShelfData::data(subject, &shelf);
     let note_toml = ShelfData::data(note, (&subject, &shelf));
@@ -56,31 +36,103 @@ pub fn note_full_object(
         ("profile", profile_config),
         ("subject", subject_toml),
         ("note", note_toml),
-        ("shelf", shelf_data)
+        ("shelf", shelf_data),
+        ("vars", vars)
     };
 
     metadata
 }
 
-pub fn create_master_note_from_subject_str(
-    subject: &str,
-    shelf: &Shelf,
-    file_globs: &Option<Vec<String>>,
-) -> Result<MasterNote, Error> {
-    let subject = Subject::from_shelf(subject, &shelf)?;
-    let subject_config = subject.get_config(&shelf).unwrap_or(SubjectConfig::new());
-    let notes = subject.get_notes_in_fs(
-        { file_globs.as_ref().unwrap_or(&subject_config.files) },
-        &shelf,
-    )?;
-
-    // Creating the master note instance and initializing the values.
-    let mut master_note = MasterNote::new(subject);
-    for note in notes {
-        master_note.push(&note);
+/// Parses the value of a template variable.
+/// The value is a TOML value (e.g., `3`, `true`, `["a", "b"]`) if it's valid or a string otherwise.
+pub fn parse_var_value(s: &str) -> toml::Value {
+    toml::from_str::<toml::value::Table>(&format!("value = {}", s))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or(toml::Value::from(s))
+}
+
+/// Asks for the missing variables of the template in the terminal.
+/// If the session is non-interactive, it fails with all of the missing variables instead.
+pub fn prompt_template_vars(
+    template: &str,
+    mut vars: toml::value::Table,
+    missing_vars: Vec<(String, TemplateVariable)>,
+) -> Result<toml::value::Table, Error> {
+    if missing_vars.is_empty() {
+        return Ok(vars);
+    }
+
+    if !io::stdin().is_terminal() {
+        let errors = missing_vars
+            .into_iter()
+            .map(|(name, _var)| Error::MissingTemplateVariableError(template.to_string(), name))
+            .collect();
+        return Err(Error::Errors(errors));
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    for (name, var) in missing_vars {
+        match &var.description {
+            Some(description) => print!("{} ({}): ", name, description),
+            None => print!("{}: ", name),
+        }
+        io::stdout().flush().map_err(Error::IoError)?;
+
+        match lines.next() {
+            Some(line) => {
+                let line = line.map_err(Error::IoError)?;
+                vars.insert(name, parse_var_value(line.trim()));
+            }
+            None => {
+                return Err(Error::MissingTemplateVariableError(
+                    template.to_string(),
+                    name,
+                ))
+            }
+        }
     }
 
-    Ok(master_note)
+    Ok(vars)
+}
+
+/// Creates the compilation environment of the subject.
+/// The environment is configured from the shelf and subject metadata with the command line options taking the highest precedence.
+pub fn compilation_environment(
+    subject: &Subject,
+    shelf: &Shelf,
+    shelf_config: &ShelfConfig,
+    profile: &Profile,
+    command: &Option<String>,
+    engine: &Option<Engine>,
+) -> CompilationEnvironment {
+    let subject_config = subject.get_config(shelf).unwrap_or_default();
+
+    let mut env = CompilationEnvironment::new(subject, shelf);
+    env.output_dirs_from_config(shelf_config, &subject_config)
+        .compiler(compiler_from_args(
+            command,
+            engine,
+            &subject_config,
+            profile,
+        ));
+
+    env
+}
+
+/// Returns the compiler with the command line options taking the highest precedence.
+pub fn compiler_from_args(
+    command: &Option<String>,
+    engine: &Option<Engine>,
+    subject_config: &SubjectConfig,
+    profile: &Profile,
+) -> Compiler {
+    match (command, engine) {
+        (Some(command), _) => Compiler::from(command.clone()),
+        (None, Some(engine)) => Compiler::from(*engine),
+        (None, None) => subject_config.compiler(profile.config()),
+    }
 }
 
 /// A generic function for writing a shelf item (as a file).
@@ -103,25 +155,42 @@ where
         file_open_options.create(true).truncate(true);
     }
 
-    let mut file = file_open_options.open(path).map_err(Error::IoError)?;
-    file.write(string.as_ref().as_bytes())
-        .map_err(Error::IoError)?;
+    // The path is included in the error since it is mostly reported along with the other items.
+    let path_error = |error: io::Error| {
+        Error::IoError(io::Error::new(
+            error.kind(),
+            format!("{} ({})", error, path.to_string_lossy()),
+        ))
+    };
+    let mut file = file_open_options.open(path).map_err(path_error)?;
+    file.write_all(string.as_ref().as_bytes())
+        .map_err(path_error)?;
     Ok(())
 }
 
-pub fn str_as_cmd<S>(string: S) -> process::Command
+/// Checks if the title has the same path with a different title from the earlier items of the same batch.
+/// The title is recorded for checking the next items.
+pub fn check_collision<S>(
+    titles: &mut HashMap<PathBuf, String>,
+    path: PathBuf,
+    title: S,
+) -> Result<(), Error>
 where
     S: AsRef<str>,
 {
-    let string = string.as_ref();
-    let mut command_iter = string.split_whitespace();
-
-    let mut command_process = process::Command::new(command_iter.next().unwrap());
-    for arg in command_iter.into_iter() {
-        command_process.arg(arg);
+    let title = title.as_ref();
+
+    match titles.get(&path) {
+        Some(existing_title) if existing_title != title => Err(Error::SlugCollisionError(
+            path,
+            existing_title.clone(),
+            title.to_string(),
+        )),
+        _ => {
+            titles.insert(path, title.to_string());
+            Ok(())
+        }
     }
-
-    command_process
 }
 
 /// Get the relative path from two paths similar to Python `os.path.relpath`.
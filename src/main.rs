@@ -1,29 +1,43 @@
+// The `Err` variant of the results is large with the errors of the template engines from the library.
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::path::PathBuf;
 use std::process;
 
 use directories;
+use lanoma_lib::archetype::Archetype;
+use lanoma_lib::compile::{
+    compile_environments, Compilable, CompilationEnvironment, CompileResult, NoopObserver,
+};
 use lanoma_lib::config::SubjectConfig;
 use lanoma_lib::error::Error;
-use lanoma_lib::masternote::MasterNote;
+use lanoma_lib::hooks::{HookEvent, HookItem, HookItemKind, Hooks};
+use lanoma_lib::masternote::MasterNoteGenerator;
 use lanoma_lib::note::Note;
-use lanoma_lib::profile::{
-    Profile, ProfileBuilder, PROFILE_MASTER_NOTE_TEMPLATE_NAME, PROFILE_NOTE_TEMPLATE_NAME,
-};
+use lanoma_lib::profile::{self, Profile, ProfileBuilder, PROFILE_NOTE_TEMPLATE_NAME};
 use lanoma_lib::shelf::{ExportOptions, Shelf, ShelfItem};
 use lanoma_lib::subjects::Subject;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use lanoma_lib::templates::TemplateRegistry;
 use structopt::StructOpt;
 
 // the modules from this crate
 mod args;
-mod compile;
 mod helpers;
 
-use crate::args::{Command, Input, Lanoma};
-use crate::compile::{Compilable, CompilationEnvironment};
+use crate::args::{Command, CompileInput, Input, Lanoma};
 
+// The exit statuses of the program.
+// Each of them are associated with a group of errors so scripts can detect the kind of the failure.
 static EXIT_STATUS: i32 = 1;
+static EXIT_COMPILATION_FAILURE: i32 = 2;
+static EXIT_INVALID_PROFILE: i32 = 3;
+static EXIT_INVALID_SUBJECT: i32 = 4;
+static EXIT_TEMPLATE_ERROR: i32 = 5;
+static EXIT_INVALID_CONFIG: i32 = 6;
+static EXIT_HOOK_FAILURE: i32 = 7;
 
 fn main() {
     let args = Lanoma::from_args();
@@ -33,27 +47,78 @@ fn main() {
         Err(e) => {
             eprintln!("{}", e);
 
-            process::exit(EXIT_STATUS)
+            process::exit(exit_status(&e))
         }
     };
 }
 
+/// Returns the exit status associated with the error.
+fn exit_status(error: &Error) -> i32 {
+    match error {
+        Error::CompilationError(_) => EXIT_COMPILATION_FAILURE,
+        Error::InvalidProfileError(_) | Error::MissingDataError(_) => EXIT_INVALID_PROFILE,
+        Error::InvalidSubjectError(_)
+        | Error::EmptyMasterNoteError(_)
+        | Error::UnknownMasterNoteError(_, _)
+        | Error::MissingMasterNoteError(_) => EXIT_INVALID_SUBJECT,
+        Error::HandlebarsTemplateError(_)
+        | Error::HandlebarsTemplateFileError(_)
+        | Error::HandlebarsRenderError(_)
+        | Error::HandlebarsTemplateRenderError(_)
+        | Error::JinjaTemplateError(_)
+        | Error::JinjaRenderError(_)
+        | Error::ScriptHelperError(_, _)
+        | Error::MissingTemplateVariableError(_, _) => EXIT_TEMPLATE_ERROR,
+        Error::TomlValueError(_)
+        | Error::TomlSerializeError(_)
+        | Error::GlobParsingError(_)
+        | Error::FrontMatterError(_, _) => EXIT_INVALID_CONFIG,
+        Error::HookError(_, _, _) => EXIT_HOOK_FAILURE,
+        // Multiple errors only have a specific exit status if all of them agree on it.
+        Error::Errors(errors) => {
+            let mut statuses = errors.iter().map(exit_status);
+            let first_status = statuses.next().unwrap_or(EXIT_STATUS);
+
+            match statuses.all(|status| status == first_status) {
+                true => first_status,
+                false => EXIT_STATUS,
+            }
+        }
+        _ => EXIT_STATUS,
+    }
+}
+
 fn parse_from_args(args: Lanoma) -> Result<(), Error> {
     let user_dirs = directories::BaseDirs::new().unwrap();
     let mut config_app_dir = user_dirs.config_dir().to_path_buf();
     config_app_dir.push(env!("CARGO_PKG_NAME"));
 
-    let shelf = match args.shelf {
+    let mut shelf = match args.shelf {
         Some(p) => Shelf::from(fs::canonicalize(p).map_err(Error::IoError)?)?,
         None => Shelf::from(env::current_dir().map_err(Error::IoError)?)?,
     };
+    // The slug strategy has to be set before anything else that uses the file names.
+    let slug = shelf.get_config()?.slug.unwrap_or_default();
+    shelf.set_slug(slug).open_index()?;
 
     let profile_path = match args.profile {
         Some(p) => p,
         None => config_app_dir,
     };
 
-    match args.cmd {
+    // The index is kept in sync even if the command has failed midway.
+    let result = run_command(args.cmd, &shelf, profile_path);
+    shelf.save_index()?;
+
+    result
+}
+
+fn run_command(
+    command: Command,
+    shelf: &Shelf,
+    profile_path: PathBuf,
+) -> Result<(), Error> {
+    match command {
         Command::Init { name } => {
             let mut profile_builder = ProfileBuilder::new();
             profile_builder.path(profile_path);
@@ -74,38 +139,103 @@ fn parse_from_args(args: Lanoma) -> Result<(), Error> {
             kind,
             not_strict,
             template,
+            vars,
         } => {
             let profile = Profile::from(&profile_path)?;
+            let hooks = Hooks::new(shelf, Some(&profile))?;
             let mut export_options = ExportOptions::new();
             export_options.strict(not_strict);
 
             match kind {
                 Input::Notes { subject, notes } => {
-                    let subject = Subject::from_shelf(&subject, &shelf)?;
-                    let notes: Vec<Note> = notes.iter().map(|note| Note::new(note)).collect();
+                    let subject = Subject::from_shelf(&subject, shelf)?;
+                    let subject_config = subject.get_config(shelf).unwrap_or(SubjectConfig::new());
+
+                    // The template can also be an archetype which renders its index template as the note.
+                    let archetype = template
+                        .as_ref()
+                        .and_then(|template| Archetype::from(template, &profile));
+
+                    // The file extension of the template name takes precedence over the subject configuration.
+                    let extension = match &archetype {
+                        Some(archetype) => archetype.extension(),
+                        None => template.as_ref().and_then(profile::template_extension),
+                    }
+                    .unwrap_or(subject_config.extension());
+                    let template = match &archetype {
+                        Some(archetype) => archetype.index_template_name(),
+                        None => template.clone().unwrap_or_else(|| {
+                            profile.default_template_name(PROFILE_NOTE_TEMPLATE_NAME, &extension)
+                        }),
+                    };
+
+                    // The variables declared in the template header are resolved before creating any of the notes.
+                    let (vars, missing_vars) = profile
+                        .template_registry()
+                        .header(&template)
+                        .resolve(&vars.into_iter().collect());
+                    let vars = helpers::prompt_template_vars(&template, vars, missing_vars)?;
+                    let notes: Vec<Note> = notes
+                        .iter()
+                        .map(|note| {
+                            let mut note = Note::new(note);
+                            note.set_slug(shelf.slug()).set_extension(&extension);
+                            note
+                        })
+                        .collect();
+                    let items: Vec<HookItem> = notes
+                        .iter()
+                        .map(|note| HookItem::note(note, &subject, shelf))
+                        .collect();
+                    hooks.run(HookEvent::PreAdd, &items)?;
 
                     let mut created_notes: Vec<Note> = vec![];
+                    let mut errors: Vec<Error> = vec![];
+                    let mut titles: HashMap<PathBuf, String> = HashMap::new();
                     for note in notes {
-                        let object = helpers::note_full_object(&profile, &shelf, &note, &subject);
-                        let template_string = profile
-                            .template_registry()
-                            .render(
-                                &template
-                                    .as_ref()
-                                    .unwrap_or(&String::from(PROFILE_NOTE_TEMPLATE_NAME)),
-                                &object,
-                            )
-                            .map_err(Error::HandlebarsRenderError)?;
+                        let path = note.path_in_shelf((&subject, shelf));
+                        if let Err(error) =
+                            helpers::check_collision(&mut titles, path, note.title())
+                                .and_then(|_| note.check_collision((&subject, shelf)))
+                        {
+                            errors.push(error);
+                            continue;
+                        }
 
-                        if helpers::write_file(
-                            note.path_in_shelf((&subject, &shelf)),
-                            template_string,
+                        let object =
+                            helpers::note_full_object(&profile, shelf, &note, &subject, &vars);
+                        let template_string =
+                            match profile.template_registry().render(&template, &object) {
+                                Ok(template_string) => template_string,
+                                Err(error) => {
+                                    // The rest of the notes would fail with the same template so it stops here.
+                                    // The hooks still run for the notes that are already created.
+                                    errors.push(error);
+                                    break;
+                                }
+                            };
+
+                        if let Err(error) = helpers::write_file(
+                            note.path_in_shelf((&subject, shelf)),
+                            note.embed_title(template_string),
                             not_strict,
-                        )
-                        .is_ok()
-                        {
-                            created_notes.push(note)
+                        ) {
+                            errors.push(error);
+                            continue;
                         }
+
+                        if let Some(archetype) = &archetype {
+                            if let Err(error) = archetype.export(
+                                &profile,
+                                &object,
+                                subject.path_in_shelf(shelf),
+                                not_strict,
+                            ) {
+                                errors.push(error);
+                            }
+                        }
+
+                        created_notes.push(note)
                     }
 
                     if created_notes.is_empty() {
@@ -115,15 +245,51 @@ fn parse_from_args(args: Lanoma) -> Result<(), Error> {
                         );
                     } else {
                         println!("Here are the notes under the subject {:?} that successfully created in the shelf.", subject.name());
-                        for note in created_notes {
+                        for note in created_notes.iter() {
                             println!("  - {:?}", note.title());
                         }
+
+                        let items: Vec<HookItem> = created_notes
+                            .iter()
+                            .map(|note| HookItem::note(note, &subject, shelf))
+                            .collect();
+                        hooks.run(HookEvent::PostAdd, &items)?;
+                    }
+
+                    if !errors.is_empty() {
+                        return Err(Error::Errors(errors));
                     }
                 }
                 Input::Subjects { subjects } => {
-                    let created_subjects: Vec<Subject> = Subject::from_vec_loose(&subjects, &shelf)
+                    let mut errors: Vec<Error> = vec![];
+                    let mut names: HashMap<PathBuf, String> = HashMap::new();
+                    let subjects = Subject::from_vec_loose(&subjects, shelf);
+                    let items: Vec<HookItem> = subjects
+                        .iter()
+                        .map(|subject| HookItem::subject(subject, shelf))
+                        .collect();
+                    hooks.run(HookEvent::PreAdd, &items)?;
+
+                    let created_subjects: Vec<Subject> = subjects
                         .into_iter()
-                        .filter(|subject| subject.export(&shelf).is_ok())
+                        .filter(|subject| {
+                            let path = subject.path_in_shelf(shelf);
+                            match helpers::check_collision(&mut names, path, subject.name())
+                                .and_then(|_| subject.check_collision(shelf))
+                            {
+                                Ok(()) => {
+                                    subject.export(shelf).is_ok()
+                                        && subject
+                                            .split_subjects()
+                                            .iter()
+                                            .all(|part| part.embed_name(shelf).is_ok())
+                                }
+                                Err(error) => {
+                                    errors.push(error);
+                                    false
+                                }
+                            }
+                        })
                         .collect();
 
                     if created_subjects.len() <= 0 {
@@ -132,205 +298,430 @@ fn parse_from_args(args: Lanoma) -> Result<(), Error> {
                         println!(
                         "Here are the subjects that have been successfully created in the shelf."
                         );
-                        for subject in created_subjects {
+                        for subject in created_subjects.iter() {
                             println!("  - {:?}", subject.full_name());
                         }
+
+                        let items: Vec<HookItem> = created_subjects
+                            .iter()
+                            .map(|subject| HookItem::subject(subject, shelf))
+                            .collect();
+                        hooks.run(HookEvent::PostAdd, &items)?;
+                    }
+
+                    if !errors.is_empty() {
+                        return Err(Error::Errors(errors));
                     }
                 }
             }
         }
-        Command::Remove { kind } => match kind {
-            Input::Subjects { subjects } => {
-                let deleted_subjects: Vec<Subject> = Subject::from_vec_loose(&subjects, &shelf)
-                    .into_iter()
-                    .filter(|subject| subject.delete(&shelf).is_ok())
-                    .collect();
+        Command::Remove { kind } => {
+            // Removing items does not need the profile so only its hooks are taken if it's valid.
+            let profile = Profile::from(&profile_path).ok();
+            let hooks = Hooks::new(shelf, profile.as_ref())?;
 
-                if deleted_subjects.is_empty() {
-                    println!("No deleted subjects.");
-                } else {
-                    for subject in deleted_subjects {
-                        println!("Subject {:?} has been deleted.", subject);
+            match kind {
+                Input::Subjects { subjects } => {
+                    let subjects = Subject::from_vec_loose(&subjects, shelf);
+                    let items: Vec<HookItem> = subjects
+                        .iter()
+                        .map(|subject| HookItem::subject(subject, shelf))
+                        .collect();
+                    hooks.run(HookEvent::PreRemove, &items)?;
+
+                    let deleted_subjects: Vec<Subject> = subjects
+                        .into_iter()
+                        .filter(|subject| subject.delete(shelf).is_ok())
+                        .collect();
+                    if let Some(mut index) = shelf.index() {
+                        for subject in deleted_subjects.iter() {
+                            index.remove_subject(subject.path());
+                        }
+                    }
+
+                    if deleted_subjects.is_empty() {
+                        println!("No deleted subjects.");
+                    } else {
+                        for subject in deleted_subjects {
+                            println!("Subject {:?} has been deleted.", subject);
+                        }
                     }
                 }
-            }
-            Input::Notes { subject, notes } => {
-                let subject = Subject::from_shelf(&subject, &shelf)?;
-                let deleted_notes: Vec<Note> = Note::from_vec_loose(&notes, &subject, &shelf)
-                    .into_iter()
-                    .filter(|note| note.delete((&subject, &shelf)).is_ok())
-                    .collect();
+                Input::Notes { subject, notes } => {
+                    let subject = Subject::from_shelf(&subject, shelf)?;
+                    let notes = Note::from_vec_loose(&notes, &subject, shelf);
+                    let items: Vec<HookItem> = notes
+                        .iter()
+                        .map(|note| HookItem::note(note, &subject, shelf))
+                        .collect();
+                    hooks.run(HookEvent::PreRemove, &items)?;
 
-                if deleted_notes.is_empty() {
-                    println!("No notes under the subject {:?} has been deleted.", subject);
-                } else {
-                    println!("The following notes has been deleted successfully:");
-                    for note in deleted_notes.iter() {
-                        println!(" - {}", note.title());
+                    let deleted_notes: Vec<Note> = notes
+                        .into_iter()
+                        .filter(|note| note.delete((&subject, shelf)).is_ok())
+                        .collect();
+                    if let Some(mut index) = shelf.index() {
+                        for note in deleted_notes.iter() {
+                            index.remove_note(note.path(&subject));
+                        }
+                    }
+
+                    if deleted_notes.is_empty() {
+                        println!("No notes under the subject {:?} has been deleted.", subject);
+                    } else {
+                        println!("The following notes has been deleted successfully:");
+                        for note in deleted_notes.iter() {
+                            println!(" - {}", note.title());
+                        }
                     }
                 }
             }
-        },
+        }
         Command::Compile {
             kind,
             thread_count,
             files,
+            tags,
             command,
+            engine,
+            fail_fast,
         } => {
-            let _profile = Profile::from(&profile_path)?;
-            let shelf_path = shelf.path();
+            let profile = Profile::from(&profile_path)?;
+            let hooks = Hooks::new(shelf, Some(&profile))?;
+            let shelf_config = shelf.get_config()?;
+            let compilation_environment = |subject: &Subject| {
+                helpers::compilation_environment(
+                    subject,
+                    shelf,
+                    &shelf_config,
+                    &profile,
+                    &command,
+                    &engine,
+                )
+            };
 
-            let compiled_notes_envs = match kind {
-                Input::Notes { subject, notes } => {
-                    let subject = Subject::from_shelf(&subject, &shelf)?;
-                    let subject_config = subject.get_config(&shelf).unwrap_or(SubjectConfig::new());
-                    let notes = Note::from_vec_loose(&notes, &subject, &shelf);
+            let tag_filter = tags.tag_filter().unwrap_or_default();
+            let mut envs: Vec<CompilationEnvironment> = vec![];
+            let item_kind = match kind {
+                CompileInput::Master { .. } => HookItemKind::Master,
+                _ => HookItemKind::Note,
+            };
+            match kind {
+                CompileInput::Notes { subject, notes } => {
+                    let subject = Subject::from_shelf(&subject, shelf)?;
+                    let notes = Note::from_vec_loose(&notes, &subject, shelf);
+                    let notes = tag_filter.filter(notes);
                     let mut compilables: Vec<Box<dyn Compilable>> = vec![];
                     for note in notes {
                         compilables.push(Box::new(note));
                     }
 
-                    let mut compiled_notes_env =
-                        CompilationEnvironment::new(subject.path_in_shelf(&shelf));
-                    compiled_notes_env
-                        .compilables(compilables)
-                        .command(command.as_ref().unwrap_or(&subject_config.command))
-                        .thread_count(thread_count as i16);
-                    vec![compiled_notes_env]
+                    let mut env = compilation_environment(&subject);
+                    env.compilables(compilables);
+                    envs.push(env);
                 }
-                Input::Subjects { subjects } => {
-                    let mut envs: Vec<CompilationEnvironment> = vec![];
-
+                CompileInput::Subjects { subjects } => {
                     for subject in subjects.iter() {
-                        let subject = Subject::from_shelf(&subject, &shelf)?;
+                        let subject = Subject::from_shelf(subject, shelf)?;
                         let subject_config =
-                            subject.get_config(&shelf).unwrap_or(SubjectConfig::new());
-                        let file_filter = files.as_ref().unwrap_or(&subject_config.files);
+                            subject.get_config(shelf).unwrap_or(SubjectConfig::new());
+                        let file_filter = files.clone().unwrap_or(subject_config.file_globs());
 
-                        let notes = subject.get_notes_in_fs(&file_filter, &shelf)?;
+                        let (notes, skipped) = subject.search_notes_in_fs(&file_filter, shelf)?;
+                        print_skipped_notes(&skipped);
+                        let notes = tag_filter.filter(notes);
                         let mut compilables: Vec<Box<dyn Compilable>> = vec![];
                         for note in notes {
                             compilables.push(Box::new(note));
                         }
 
-                        let mut env = CompilationEnvironment::new(subject.path_in_shelf(&shelf));
-                        env.command(command.as_ref().unwrap_or(&subject_config.command))
-                            .compilables(compilables)
-                            .thread_count(thread_count as i16);
-
+                        let mut env = compilation_environment(&subject);
+                        env.compilables(compilables);
                         envs.push(env);
                     }
-
-                    envs
                 }
-            };
+                CompileInput::Master { subjects, name } => {
+                    let mut generator = MasterNoteGenerator::new(shelf);
+                    if let Some(name) = name {
+                        generator.name(name);
+                    }
 
-            compiled_notes_envs
-                .into_iter()
-                .filter(|comp_env| !comp_env.compilables.is_empty())
-                .map(|comp_env| comp_env.compile())
-                .filter_map(|compile_result| compile_result.ok())
-                .for_each(|compile_result| {
-                    println!(
-                        "\n\n----\nAt {:?}:\n----\n",
-                        helpers::relative_path_from(&compile_result.path, &shelf_path)
-                            .unwrap_or(compile_result.path)
-                    );
+                    for subject in subjects.iter() {
+                        let subject = Subject::from_shelf(subject, shelf)?;
+                        let master_note = generator.open(&subject)?;
 
-                    if !compile_result.compiled.is_empty() {
-                        println!("Notes that succeeded to compile:");
-                        for compiled in compile_result.compiled {
-                            println!("  - {}", compiled);
-                        }
+                        let mut env = compilation_environment(&subject);
+                        env.compilables(vec![Box::new(master_note)]);
+                        envs.push(env);
                     }
+                }
+            };
 
-                    if !compile_result.failed.is_empty() {
-                        println!("Notes that failed to compile:");
-                        for failed in compile_result.failed {
-                            println!("  - {}", failed);
-                        }
-                    }
-                })
+            for env in envs.iter_mut() {
+                env.fail_fast(fail_fast);
+            }
+
+            let items = HookItem::from_environments(item_kind, &envs);
+            hooks.run(HookEvent::PreCompile, &items.concat())?;
+
+            let compile_results =
+                compile_environments(envs, &profile, &NoopObserver, thread_count as i16)?;
+            let failed_count = print_compile_results(&compile_results, shelf);
+            hooks.run(
+                HookEvent::PostCompile,
+                &HookItem::mark_compiled(items, &compile_results),
+            )?;
+            if failed_count > 0 {
+                return Err(Error::CompilationError(failed_count));
+            }
         }
         Command::Master {
             subjects,
             skip_compilation,
+            thread_count,
+            recursive,
+            name,
             files,
+            tags,
             template,
             command,
+            engine,
+            fail_fast,
         } => {
             let profile = Profile::from(&profile_path)?;
+            let hooks = Hooks::new(shelf, Some(&profile))?;
+            let shelf_config = shelf.get_config()?;
 
-            let compiled_master_notes: Vec<MasterNote> = subjects
-                .into_par_iter()
-                .filter_map(|subject| {
-                    helpers::create_master_note_from_subject_str(&subject, &shelf, &files).ok()
-                })
-                .filter(|master_note| {
-                    if master_note.notes().is_empty() {
-                        return false;
-                    }
+            let mut generator = MasterNoteGenerator::new(shelf);
+            generator.recursive(recursive);
+            if let Some(name) = name {
+                generator.name(name);
+            }
+            if let Some(files) = files {
+                generator.files(files);
+            }
+            if let Some(tag_filter) = tags.tag_filter() {
+                generator.tag_filter(tag_filter);
+            }
+            if let Some(template) = template {
+                generator.template(template);
+            }
 
-                    let master_note_object =
-                        helpers::master_note_full_object(&profile, &shelf, &master_note);
-                    // Calling the unwrap function here since once a Handlebars template has an erro, it will most likely have an error for the rest of the notes.
-                    let resulting_string = profile
-                        .template_registry()
-                        .render(
-                            &template
-                                .as_ref()
-                                .unwrap_or(&PROFILE_MASTER_NOTE_TEMPLATE_NAME.into()),
-                            &master_note_object,
-                        )
-                        .map_err(Error::HandlebarsRenderError)
-                        .unwrap();
-
-                    helpers::write_file(master_note.path_in_shelf(&shelf), resulting_string, false)
-                        .is_ok()
-                })
-                .filter(|master_note| {
-                    if !skip_compilation {
-                        let original_dir = env::current_dir().map_err(Error::IoError).unwrap();
-                        let compilation_dst = master_note.subject().path_in_shelf(&shelf);
-                        let config = master_note
-                            .subject()
-                            .get_config(&shelf)
-                            .unwrap_or(SubjectConfig::new());
-
-                        env::set_current_dir(&compilation_dst)
-                            .map_err(Error::IoError)
-                            .unwrap();
-                        let mut master_note_compilation_cmd =
-                            master_note.to_command(command.as_ref().unwrap_or(&config.command));
-                        let output = master_note_compilation_cmd.output().unwrap();
-                        env::set_current_dir(original_dir)
-                            .map_err(Error::IoError)
-                            .unwrap();
-
-                        output.status.success()
-                    } else {
-                        false
+            let master_note_result = generator.generate(&subjects, &profile);
+            let mut errors: Vec<Error> = vec![];
+            print_skipped_notes(&master_note_result.skipped);
+
+            if !master_note_result.generated.is_empty() {
+                println!("Master notes that are successfully generated:");
+                for master_note in master_note_result.generated.iter() {
+                    println!("  - {:?}", master_note.subject().full_name());
+
+                    for note in master_note.notes() {
+                        println!("    - {:?}", note.title());
                     }
-                })
-                .collect();
+                }
+            }
 
-            for master_note in compiled_master_notes {
-                println!(
-                        "\n{:?} has successfully compiled a master note\nwith the following filtered notes.",
-                        master_note.subject().full_name()
+            if !master_note_result.failed.is_empty() {
+                println!("Subjects that failed to generate a master note:");
+                for (subject, error) in master_note_result.failed {
+                    println!("  - {:?}", subject.full_name());
+                    errors.push(error);
+                }
+            }
+
+            if !master_note_result.generated.is_empty() {
+                let items: Vec<HookItem> = master_note_result
+                    .generated
+                    .iter()
+                    .map(|master_note| HookItem::master_note(master_note, shelf))
+                    .collect();
+                hooks.run(HookEvent::PostMaster, &items)?;
+            }
+
+            if !skip_compilation {
+                let mut envs: Vec<CompilationEnvironment> = vec![];
+                for master_note in master_note_result.generated {
+                    let mut env = helpers::compilation_environment(
+                        master_note.subject(),
+                        shelf,
+                        &shelf_config,
+                        &profile,
+                        &command,
+                        &engine,
                     );
+                    env.compilables(vec![Box::new(master_note)])
+                        .fail_fast(fail_fast);
+                    envs.push(env);
+                }
 
-                for note in master_note.notes() {
-                    println!("  - {:?}", note.title());
+                let items = HookItem::from_environments(HookItemKind::Master, &envs);
+                hooks.run(HookEvent::PreCompile, &items.concat())?;
+
+                let compile_results =
+                    compile_environments(envs, &profile, &NoopObserver, thread_count as i16)?;
+                let failed_count = print_compile_results(&compile_results, shelf);
+                hooks.run(
+                    HookEvent::PostCompile,
+                    &HookItem::mark_compiled(items, &compile_results),
+                )?;
+                if failed_count > 0 {
+                    errors.push(Error::CompilationError(failed_count));
+                }
+            }
+
+            if !errors.is_empty() {
+                return Err(Error::Errors(errors));
+            }
+        }
+        Command::Reindex => {
+            let skipped = shelf.reindex()?;
+            print_skipped_notes(&skipped);
+
+            if let Some(index) = shelf.index() {
+                println!(
+                    "Indexed {} subject(s) with metadata and {} note(s) in the shelf.",
+                    index.subjects().len(),
+                    index.notes().len()
+                );
+            }
+        }
+        Command::List {
+            subjects,
+            sort,
+            date,
+            reverse,
+            tags,
+        } => {
+            let subjects = match subjects.is_empty() {
+                true => {
+                    // The output directories of the shelf are not considered as subjects.
+                    let shelf_config = shelf.get_config()?;
+                    let output_dirs: Vec<_> = vec![shelf_config.output_dir, shelf_config.pdf_dir]
+                        .into_iter()
+                        .flatten()
+                        .collect();
+
+                    Subject::new("")
+                        .get_all_subjects_in_fs(shelf)?
+                        .into_iter()
+                        .filter(|subject| {
+                            !output_dirs
+                                .iter()
+                                .any(|output_dir| subject.path().starts_with(output_dir))
+                        })
+                        .collect()
+                }
+                false => {
+                    let mut all_subjects: Vec<Subject> = vec![];
+                    for subject in subjects.iter() {
+                        all_subjects.push(Subject::from_shelf(subject, shelf)?);
+                    }
+
+                    all_subjects
+                }
+            };
+            let tag_filter = tags.tag_filter().unwrap_or_default();
+            let sort_by_date = date || sort.as_deref() == Some("date");
+
+            for subject in subjects {
+                let subject_config = subject.get_config(shelf).unwrap_or(SubjectConfig::new());
+                let (notes, skipped) =
+                    subject.search_notes_in_fs(&subject_config.file_globs(), shelf)?;
+                print_skipped_notes(&skipped);
+                let mut notes = tag_filter.filter(notes);
+                if notes.is_empty() && !tag_filter.is_empty() {
+                    continue;
+                }
+
+                match sort_by_date {
+                    true => notes.sort_by_cached_key(|note| {
+                        fs::metadata(note.path_in_shelf((&subject, shelf)))
+                            .and_then(|metadata| metadata.modified())
+                            .ok()
+                    }),
+                    false => notes.sort_by_cached_key(|note| note.title().to_lowercase()),
+                }
+                if reverse {
+                    notes.reverse();
+                }
+
+                println!("{}", subject.full_name());
+                for note in notes {
+                    match note.tags().is_empty() {
+                        true => println!("  - {}", note.title()),
+                        false => println!("  - {} [{}]", note.title(), note.tags().join(", ")),
+                    }
                 }
             }
         }
-        _ => (),
     }
 
     Ok(())
 }
 
+/// Prints the warnings for the notes that are skipped while searching the notes of a subject.
+fn print_skipped_notes(skipped: &[Error]) {
+    for error in skipped {
+        eprintln!("Warning: {} The note is skipped.", error);
+    }
+}
+
+/// Prints the compilation results grouped by their path.
+/// Returns the number of the notes that failed to compile.
+fn print_compile_results(
+    compile_results: &[CompileResult],
+    shelf: &Shelf,
+) -> usize {
+    let mut failed_count = 0;
+
+    for compile_result in compile_results {
+        if compile_result.compiled.is_empty()
+            && compile_result.failed.is_empty()
+            && compile_result.skipped.is_empty()
+        {
+            continue;
+        }
+
+        println!(
+            "\n\n----\nAt {:?}:\n----\n",
+            helpers::relative_path_from(&compile_result.path, shelf.path())
+                .unwrap_or(compile_result.path.clone())
+        );
+
+        if !compile_result.compiled.is_empty() {
+            println!("Notes that succeeded to compile:");
+            for compiled in compile_result.compiled.iter() {
+                println!("  - {}", compiled);
+            }
+        }
+
+        if !compile_result.failed.is_empty() {
+            failed_count += compile_result.failed.len();
+
+            println!("Notes that failed to compile:");
+            for failed in compile_result.failed.iter() {
+                println!(
+                    "  - {} (step {}: `{}`)\n    {}",
+                    failed.compilable,
+                    failed.step.index + 1,
+                    failed.step.command,
+                    failed.step.error
+                );
+            }
+        }
+
+        if !compile_result.skipped.is_empty() {
+            println!("Notes that are skipped from an earlier failure:");
+            for skipped in compile_result.skipped.iter() {
+                println!("  - {}", skipped);
+            }
+        }
+    }
+
+    failed_count
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,4 +733,57 @@ mod tests {
 
         assert_eq!(parse_from_args(command_args).is_err(), true);
     }
+
+    #[test]
+    pub fn exit_status_test() {
+        assert_eq!(
+            exit_status(&Error::CompilationError(2)),
+            EXIT_COMPILATION_FAILURE
+        );
+        assert_eq!(
+            exit_status(&Error::Errors(vec![
+                Error::CompilationError(1),
+                Error::CompilationError(3)
+            ])),
+            EXIT_COMPILATION_FAILURE
+        );
+        assert_eq!(
+            exit_status(&Error::Errors(vec![
+                Error::CompilationError(1),
+                Error::ValueError
+            ])),
+            EXIT_STATUS
+        );
+    }
+
+    #[test]
+    pub fn template_vars_test() {
+        let command_args_as_vec = vec![
+            "lanoma",
+            "add",
+            "--var",
+            "lecturer=Dr. Lee",
+            "--var",
+            "week=3",
+            "notes",
+            "Calculus",
+            "--",
+            "Limits",
+        ];
+        let vars = match Lanoma::from_iter(command_args_as_vec.iter()).cmd {
+            Command::Add { vars, .. } => vars,
+            _ => panic!("The command should be parsed as the add subcommand."),
+        };
+
+        assert_eq!(
+            vars,
+            vec![
+                ("lecturer".to_string(), toml::Value::from("Dr. Lee")),
+                ("week".to_string(), toml::Value::from(3))
+            ]
+        );
+        assert!(
+            Lanoma::from_iter_safe(vec!["lanoma", "add", "--var", "=3", "subjects", "A"]).is_err()
+        );
+    }
 }